@@ -0,0 +1,132 @@
+use bevy::{audio::Volume, prelude::*};
+
+use crate::{controls::InputActions, BrickHitEvent, CollisionEvent, CollisionSurface};
+
+// ** Collision audio **
+// play_collision_sounds reads the CollisionEvents sent by check_ball_collisions and collide_balls (for the paddle, walls and balls)
+// and the BrickHitEvents sent by damage_bricks (for bricks), and plays the matching sound at the volume from AudioSettings.
+
+// every surface has its own sound
+const PADDLE_SOUND: &str = "sounds/paddle.wav";
+const WALL_SOUND: &str = "sounds/wall.wav";
+const BALL_SOUND: &str = "sounds/breakout_collision.ogg";
+const BRICK_DAMAGED_SOUND: &str = "sounds/brick_damaged.wav";
+const BRICK_DESTROYED_SOUND: &str = "sounds/brick_destroyed.wav";
+
+// how much the volume changes on each press of a volume key
+const VOLUME_STEP: f32 = 0.1;
+
+// volumes go from 0.0 (silent) to 1.0 (full volume)
+#[derive(Resource)]
+pub struct AudioSettings {
+    // applies to every sound in the game
+    pub master_volume: f32,
+    // applies to sound effects (the collision sounds) on top of the master volume
+    pub sfx_volume: f32,
+    pub muted: bool,
+}
+
+impl AudioSettings {
+    // moves the master volume one VOLUME_STEP up (direction 1.0) or down (direction -1.0), staying between 0.0 and 1.0
+    pub fn step_master_volume(&mut self, direction: f32) {
+        self.master_volume = (self.master_volume + direction * VOLUME_STEP).clamp(0.0, 1.0);
+    }
+
+    // the same for the sound effects volume
+    pub fn step_sfx_volume(&mut self, direction: f32) {
+        self.sfx_volume = (self.sfx_volume + direction * VOLUME_STEP).clamp(0.0, 1.0);
+    }
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master_volume: 1.0,
+            sfx_volume: 1.0,
+            muted: false,
+        }
+    }
+}
+
+// the loaded sound for every kind of collision
+#[derive(Resource)]
+pub struct CollisionSounds {
+    paddle: Handle<AudioSource>,
+    wall: Handle<AudioSource>,
+    ball: Handle<AudioSource>,
+    brick_damaged: Handle<AudioSource>,
+    brick_destroyed: Handle<AudioSource>,
+}
+
+pub fn load_collision_sounds(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(CollisionSounds {
+        paddle: asset_server.load(PADDLE_SOUND),
+        wall: asset_server.load(WALL_SOUND),
+        ball: asset_server.load(BALL_SOUND),
+        brick_damaged: asset_server.load(BRICK_DAMAGED_SOUND),
+        brick_destroyed: asset_server.load(BRICK_DESTROYED_SOUND),
+    });
+}
+
+pub fn play_collision_sounds(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut brick_hit_events: EventReader<BrickHitEvent>,
+    sounds: Res<CollisionSounds>,
+    settings: Res<AudioSettings>,
+) {
+    if settings.muted {
+        // the events still have to be read, or they would all be played at once after unmuting
+        collision_events.clear();
+        brick_hit_events.clear();
+        return;
+    }
+
+    let volume = settings.master_volume * settings.sfx_volume;
+    for collision in collision_events.iter() {
+        match collision.surface {
+            CollisionSurface::Paddle => play_sound(&mut commands, &sounds.paddle, volume),
+            CollisionSurface::Wall => play_sound(&mut commands, &sounds.wall, volume),
+            CollisionSurface::Ball => play_sound(&mut commands, &sounds.ball, volume),
+            // bricks are played from the BrickHitEvents below, which say if the brick broke
+            CollisionSurface::Brick => {}
+        }
+    }
+    for brick_hit in brick_hit_events.iter() {
+        if brick_hit.destroyed {
+            play_sound(&mut commands, &sounds.brick_destroyed, volume);
+        } else {
+            play_sound(&mut commands, &sounds.brick_damaged, volume);
+        }
+    }
+}
+
+fn play_sound(commands: &mut Commands, source: &Handle<AudioSource>, volume: f32) {
+    // each sound is its own entity, DESPAWN removes it once the sound has finished playing
+    commands.spawn(AudioBundle {
+        source: source.clone(),
+        settings: PlaybackSettings {
+            volume: Volume::new_relative(volume),
+            ..PlaybackSettings::DESPAWN
+        },
+    });
+}
+
+// mutes and unmutes, and changes the master and sound effects volumes (0, -, =, [ and ] by default, see controls.rs)
+pub fn change_volume_on_input(actions: Res<InputActions>, mut settings: ResMut<AudioSettings>) {
+    if actions.toggle_mute {
+        settings.muted = !settings.muted;
+    }
+    if actions.master_volume_down {
+        settings.step_master_volume(-1.0);
+    }
+    if actions.master_volume_up {
+        settings.step_master_volume(1.0);
+    }
+    if actions.sfx_volume_down {
+        settings.step_sfx_volume(-1.0);
+    }
+    if actions.sfx_volume_up {
+        settings.step_sfx_volume(1.0);
+    }
+}
@@ -0,0 +1,223 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::GameConfig, Ball, Brick, CollisionEvent, CollisionSurface, Velocity};
+
+// ** Ball speed progression **
+// A life starts with the balls at the level's ball_speed. They then get faster:
+// - every speed_up_paddle_hits times a ball bounces off the paddle,
+// - the first time a ball reaches the upper speed_up_upper_rows rows of bricks (or the ceiling),
+// - every speed_up_interval seconds.
+// The speed never goes above ball_max_speed, and goes back to the level's ball_speed when a life is lost.
+// The rules are set in the speed_up section of config/game.ron, see config.rs. They are read every tick,
+// so an edit to the file (see hot_reload.rs) applies at once.
+
+// the speed every ball moves at, and what has been counted towards the next speed up
+#[derive(Resource, Default)]
+pub struct BallSpeed {
+    pub current: f32,
+    // the speed the life started at
+    base: f32,
+    paddle_hits: usize,
+    // seconds since the last speed up over time
+    elapsed: f32,
+    reached_upper_rows: bool,
+}
+
+impl BallSpeed {
+    // back to `base` speed with nothing counted yet, at the start of a round and after a lost life
+    pub fn reset(&mut self, base: f32, max: f32) {
+        *self = BallSpeed {
+            current: base.min(max),
+            base,
+            ..default()
+        };
+    }
+
+    // changes the starting speed of the life (when the level file is edited while playing) and keeps what was gained since
+    pub fn rebase(&mut self, base: f32, max: f32) {
+        self.current = (self.current - self.base + base).clamp(0.0, max);
+        self.base = base;
+    }
+
+    fn speed_up(&mut self, step: f32, max: f32) {
+        self.current = (self.current + step).min(max);
+    }
+}
+
+// BallSpeed as it is written in the saved run, so a continued run carries on at the same speed.
+// Kept apart from the resource so a change to BallSpeed doesn't change the save file by accident
+#[derive(Serialize, Deserialize)]
+pub struct SavedBallSpeed {
+    current: f32,
+    base: f32,
+    paddle_hits: usize,
+    elapsed: f32,
+    reached_upper_rows: bool,
+}
+
+impl From<&BallSpeed> for SavedBallSpeed {
+    fn from(ball_speed: &BallSpeed) -> Self {
+        SavedBallSpeed {
+            current: ball_speed.current,
+            base: ball_speed.base,
+            paddle_hits: ball_speed.paddle_hits,
+            elapsed: ball_speed.elapsed,
+            reached_upper_rows: ball_speed.reached_upper_rows,
+        }
+    }
+}
+
+impl From<SavedBallSpeed> for BallSpeed {
+    fn from(saved: SavedBallSpeed) -> Self {
+        BallSpeed {
+            current: saved.current,
+            base: saved.base,
+            paddle_hits: saved.paddle_hits,
+            elapsed: saved.elapsed,
+            reached_upper_rows: saved.reached_upper_rows,
+        }
+    }
+}
+
+// counts paddle hits, upper row hits and time, and raises the speed when a rule is met
+pub fn speed_up_balls(
+    config: Res<GameConfig>,
+    time_step: Res<FixedTime>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut ball_speed: ResMut<BallSpeed>,
+    ball_query: Query<(), With<Ball>>,
+    brick_query: Query<&Transform, With<Brick>>,
+) {
+    let max = config.ball_max_speed;
+    // a lower max_speed in an edited config applies straight away
+    if ball_speed.current > max {
+        ball_speed.current = max;
+    }
+    for collision in collision_events.iter() {
+        // laser bolts send CollisionEvents for the bricks they hit too, they don't count
+        if !ball_query.contains(collision.ball) {
+            continue;
+        }
+        if collision.surface == CollisionSurface::Paddle {
+            ball_speed.paddle_hits += 1;
+            if ball_speed
+                .paddle_hits
+                .is_multiple_of(config.speed_up_paddle_hits)
+            {
+                ball_speed.speed_up(config.speed_up_paddle_step, max);
+            }
+        }
+        // only a brick of the upper rows or the ceiling (a wall hit from below) shows the ball got up there,
+        // not two balls bumping into each other or a side wall
+        let reached_upper_rows = match collision.surface {
+            CollisionSurface::Brick => brick_query
+                .get(collision.other)
+                .is_ok_and(|transform| in_upper_rows(&config, transform.translation.y)),
+            CollisionSurface::Wall => collision.normal.y < 0.0,
+            CollisionSurface::Paddle | CollisionSurface::Ball => false,
+        };
+        if !ball_speed.reached_upper_rows && reached_upper_rows {
+            ball_speed.reached_upper_rows = true;
+            ball_speed.speed_up(config.speed_up_upper_rows_step, max);
+        }
+    }
+
+    ball_speed.elapsed += time_step.period.as_secs_f32();
+    if ball_speed.elapsed >= config.speed_up_interval {
+        // an edited config can make the interval much shorter than the time already counted, so this starts over
+        // instead of subtracting the interval
+        ball_speed.elapsed = 0.0;
+        ball_speed.speed_up(config.speed_up_interval_step, max);
+    }
+}
+
+// true for a brick (by the y of its center) in the upper speed_up_upper_rows rows. The brick is checked rather than
+// the ball, because a ball hitting the bottom of a brick is lower than the brick's row
+fn in_upper_rows(config: &GameConfig, brick_y: f32) -> bool {
+    // speed_up_upper_rows is at least 1, config.rs checks it
+    let lowest_upper_row_y = config.brick_position(config.speed_up_upper_rows - 1, 0).y;
+    // halfway to the next row down, so rounding in the brick positions doesn't matter
+    brick_y >= lowest_upper_row_y - (config.brick_size.y + config.gap_between_bricks) * 0.5
+}
+
+// gives every ball the current speed, keeping its direction.
+// Bounces only ever turn a ball, so this is also what keeps balls at the same speed after bouncing off each other
+pub fn apply_ball_speed(ball_speed: Res<BallSpeed>, mut ball_query: Query<&mut Velocity, With<Ball>>) {
+    for mut velocity in &mut ball_query {
+        // normalize_or_zero() gives a direction of length 1 (or zero for a ball that isn't moving at all)
+        let direction = velocity.normalize_or_zero();
+        velocity.0 = direction * ball_speed.current;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bricks_of_the_lowest_upper_row_count() {
+        let config = GameConfig::default();
+        let row = config.speed_up_upper_rows - 1;
+        assert!(in_upper_rows(&config, config.brick_position(row, 0).y));
+        assert!(in_upper_rows(&config, config.brick_position(0, 3).y));
+    }
+
+    #[test]
+    fn hit_on_the_bottom_of_the_lowest_upper_row_speeds_up() {
+        let config = GameConfig::default();
+        let brick_center = config.brick_position(config.speed_up_upper_rows - 1, 0);
+        let ball_radius = config.ball_size.y * 0.5;
+
+        let mut world = World::new();
+        let ball = world
+            .spawn(Ball {
+                radius: ball_radius,
+            })
+            .id();
+        let brick = world
+            .spawn((
+                Brick {
+                    health: 1,
+                    max_health: 1,
+                    hit_score: 1,
+                    destroy_score: 0,
+                    kind: default(),
+                },
+                Transform::from_translation(brick_center.extend(0.0)),
+            ))
+            .id();
+        let mut ball_speed = BallSpeed::default();
+        ball_speed.reset(config.ball_speed, config.ball_max_speed);
+        world.insert_resource(ball_speed);
+        world.insert_resource(FixedTime::default());
+        world.init_resource::<Events<CollisionEvent>>();
+        // the ball touches the bottom side of the brick, so its center is below the brick's row
+        world.send_event(CollisionEvent {
+            ball,
+            other: brick,
+            surface: CollisionSurface::Brick,
+            normal: Vec2::NEG_Y,
+            position: brick_center - Vec2::new(0.0, config.brick_size.y * 0.5 + ball_radius),
+        });
+        world.insert_resource(config.clone());
+
+        let mut schedule = Schedule::new();
+        schedule.add_systems(speed_up_balls);
+        schedule.run(&mut world);
+
+        let ball_speed = world.resource::<BallSpeed>();
+        assert!(ball_speed.reached_upper_rows);
+        assert_eq!(
+            ball_speed.current,
+            config.ball_speed + config.speed_up_upper_rows_step
+        );
+    }
+
+    #[test]
+    fn bricks_below_the_upper_rows_do_not_count() {
+        let config = GameConfig::default();
+        let row = config.speed_up_upper_rows;
+        assert!(!in_upper_rows(&config, config.brick_position(row, 0).y));
+    }
+}
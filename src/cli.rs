@@ -0,0 +1,63 @@
+// ** Command line **
+// cargo run -- [--headless [ROUNDS]] [--seed SEED]
+// --headless plays ROUNDS rounds (DEFAULT_HEADLESS_ROUNDS when left out) without a window, see headless.rs.
+// --seed starts the random numbers from SEED, so a game can be played again, see rng.rs.
+// Without any option the game starts as usual.
+
+const USAGE: &str = "usage: cargo run -- [--headless [ROUNDS]] [--seed SEED]";
+const DEFAULT_HEADLESS_ROUNDS: usize = 100;
+
+// the options given on the command line
+#[derive(Default, Debug)]
+pub struct CommandLine {
+    // Some(number of rounds to play) for headless mode
+    pub headless_rounds: Option<usize>,
+    pub seed: Option<u64>,
+}
+
+// reads the command line. A mistake in it prints what went wrong and the usage, and stops the program
+pub fn parse_command_line() -> CommandLine {
+    match parse_args(std::env::args().skip(1)) {
+        Ok(command_line) => command_line,
+        Err(message) => {
+            eprintln!("{message}\n{USAGE}");
+            std::process::exit(2);
+        }
+    }
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<CommandLine, String> {
+    let mut command_line = CommandLine::default();
+    // peekable() lets us look at the next argument before deciding to take it
+    let mut args = args.peekable();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--headless" => {
+                // the number of rounds can be left out
+                let rounds = match args.next_if(|next| !next.starts_with("--")) {
+                    Some(text) => match text.parse() {
+                        Ok(rounds) if rounds > 0 => rounds,
+                        _ => {
+                            return Err(format!(
+                                "--headless needs a number of rounds above 0, not {text}"
+                            ))
+                        }
+                    },
+                    None => DEFAULT_HEADLESS_ROUNDS,
+                };
+                command_line.headless_rounds = Some(rounds);
+            }
+            "--seed" => {
+                let Some(text) = args.next() else {
+                    return Err("--seed needs a number".to_string());
+                };
+                let seed = text
+                    .parse()
+                    .map_err(|_| format!("--seed needs a whole number from 0 up, not {text}"))?;
+                command_line.seed = Some(seed);
+            }
+            _ => return Err(format!("unknown option {arg}")),
+        }
+    }
+    Ok(command_line)
+}
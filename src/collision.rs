@@ -0,0 +1,441 @@
+use bevy::prelude::*;
+
+// ** Swept collisions **
+// Moving the ball first and then checking if it overlaps something misses hits when the ball is fast:
+// in one tick it can jump from one side of a thin wall or brick to the other.
+// Instead, the path of the ball during the tick is checked, and the first thing it touches on the way is found.
+//
+// A box of size A moving into a box of size B touches it at the same moment as a single point (the center of A)
+// moving into a box of size A + B. So every check below is done with a point against a grown box.
+// The ball is round, so the grown box has rounded corners: near a corner of a brick the ball touches the corner
+// itself, and bounces off in the direction pointing from that corner to the center of the ball.
+
+// where the ball touches something on its way
+pub struct Contact {
+    // how far along the motion the contact happens: 0.0 at the start, 1.0 at the end
+    pub time: f32,
+    // the direction pointing straight out of the side that was hit, e.g. Vec2::Y for the top side
+    pub normal: Vec2,
+}
+
+// finds when a circle moving by `motion` first touches a box that doesn't move.
+// Returns None when it doesn't touch it during this motion.
+// A circle that already overlaps the box and moves further into it touches it at time 0.0
+pub fn sweep_circle(
+    start: Vec2,
+    radius: f32,
+    motion: Vec2,
+    box_center: Vec2,
+    box_half_size: Vec2,
+) -> Option<Contact> {
+    // first treat the circle like a square around it
+    let contact = sweep_aabb(
+        start,
+        Vec2::splat(radius),
+        motion,
+        box_center,
+        box_half_size,
+    )?;
+
+    // if the square touches a side of the box, the circle touches it at the same moment
+    let touch_point = start + motion * contact.time;
+    let offset = touch_point - box_center;
+    if offset.x.abs() <= box_half_size.x || offset.y.abs() <= box_half_size.y {
+        return Some(contact);
+    }
+
+    // otherwise the square only touched near a corner, where the circle might still miss.
+    // signum() gives -1.0 or 1.0, which picks the corner on the side of the touch point
+    let corner = box_center + box_half_size * offset.signum();
+    let time = sweep_point_circle(start, motion, corner, radius)?;
+    Some(Contact {
+        time,
+        normal: (start + motion * time - corner).normalize(),
+    })
+}
+
+// finds when a box (center `start`, half size `half_size`) moving by `motion` first touches a box that doesn't move.
+// Returns None when it doesn't touch it during this motion.
+// A box that already overlaps the other one touches it at time 0.0, through the nearest side, if it moves further in
+fn sweep_aabb(
+    start: Vec2,
+    half_size: Vec2,
+    motion: Vec2,
+    box_center: Vec2,
+    box_half_size: Vec2,
+) -> Option<Contact> {
+    let grown_min = box_center - box_half_size - half_size;
+    let grown_max = box_center + box_half_size + half_size;
+
+    // "slab" method: on each axis, find the time the point enters and leaves the grown box.
+    // The point is inside the box once it has entered on both axes and hasn't left on either.
+    let mut entry_time = f32::NEG_INFINITY;
+    let mut exit_time = f32::INFINITY;
+    let mut normal = Vec2::ZERO;
+
+    for axis in 0..2 {
+        if motion[axis] == 0.0 {
+            // not moving on this axis: it either always lines up with the box on this axis or never does
+            if start[axis] <= grown_min[axis] || start[axis] >= grown_max[axis] {
+                return None;
+            }
+            continue;
+        }
+
+        let time_to_min = (grown_min[axis] - start[axis]) / motion[axis];
+        let time_to_max = (grown_max[axis] - start[axis]) / motion[axis];
+        let axis_entry = time_to_min.min(time_to_max);
+        let axis_exit = time_to_min.max(time_to_max);
+
+        if axis_entry > entry_time {
+            entry_time = axis_entry;
+            // the side that is hit faces against the motion
+            normal = Vec2::ZERO;
+            normal[axis] = -motion[axis].signum();
+        }
+        exit_time = exit_time.min(axis_exit);
+    }
+
+    // entered before the motion started and not left yet: the point starts inside the grown box
+    if entry_time < 0.0 && exit_time > 0.0 {
+        // the nearest side is the one the point is least deep behind
+        let to_min = start - grown_min;
+        let to_max = grown_max - start;
+        let depth = to_min.min(to_max);
+        let axis = if depth.x < depth.y { 0 } else { 1 };
+        let mut normal = Vec2::ZERO;
+        normal[axis] = if to_min[axis] < to_max[axis] {
+            -1.0
+        } else {
+            1.0
+        };
+        // one that is already on its way out doesn't touch it again
+        if motion.dot(normal) >= 0.0 {
+            return None;
+        }
+        return Some(Contact { time: 0.0, normal });
+    }
+
+    // the contact must happen during this motion (between 0.0 and 1.0) and before the point leaves the box again
+    if entry_time > exit_time || !(0.0..=1.0).contains(&entry_time) {
+        return None;
+    }
+
+    Some(Contact {
+        time: entry_time,
+        normal,
+    })
+}
+
+// finds when a point moving by `motion` gets within `radius` of `center`, as a fraction of the motion
+fn sweep_point_circle(start: Vec2, motion: Vec2, center: Vec2, radius: f32) -> Option<f32> {
+    // the point is at start + motion * t. Solving |start + motion * t - center| = radius for t
+    // gives the quadratic equation a * t^2 + 2 * b * t + c = 0
+    let to_start = start - center;
+    let a = motion.length_squared();
+    let b = to_start.dot(motion);
+    let c = to_start.length_squared() - radius * radius;
+    if a == 0.0 {
+        return None;
+    }
+    if c <= 0.0 {
+        // already within `radius`: touching at once if it moves closer (b < 0.0), not at all if it moves away
+        return (b < 0.0).then_some(0.0);
+    }
+
+    let discriminant = b * b - a * c;
+    if discriminant < 0.0 {
+        // the path passes the circle without touching it
+        return None;
+    }
+    // the smaller solution is the moment the point arrives at the circle (the other one is when it leaves)
+    let time = (-b - discriminant.sqrt()) / a;
+    (0.0..=1.0).contains(&time).then_some(time)
+}
+
+// when a circle is already overlapping a box, returns the shortest move that gets it out.
+// Returns None if they don't overlap.
+pub fn circle_penetration(
+    center: Vec2,
+    radius: f32,
+    box_center: Vec2,
+    box_half_size: Vec2,
+) -> Option<Vec2> {
+    let offset = center - box_center;
+    // the point of the box closest to the center of the circle
+    let closest = offset.clamp(-box_half_size, box_half_size);
+    let from_closest = offset - closest;
+    let distance = from_closest.length();
+    if distance >= radius {
+        return None;
+    }
+
+    if distance > 0.0 {
+        // push the circle away from the closest point until it only just touches it
+        return Some(from_closest / distance * (radius - distance));
+    }
+
+    // the center of the circle is inside the box: push it out through the nearest side
+    let overlap = box_half_size - offset.abs() + Vec2::splat(radius);
+    if overlap.x < overlap.y {
+        Some(Vec2::new(overlap.x * offset.x.signum(), 0.0))
+    } else {
+        Some(Vec2::new(0.0, overlap.y * offset.y.signum()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // floats that went through a few calculations are compared with a little room for rounding
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{a} is not close to {b}");
+    }
+
+    fn assert_close_vec(a: Vec2, b: Vec2) {
+        assert!((a - b).length() < 1e-4, "{a} is not close to {b}");
+    }
+
+    #[test]
+    fn circle_hits_bottom_face() {
+        // the bottom of the box is at y = -10, the ball touches it when its center is at y = -15
+        let contact = sweep_circle(
+            Vec2::new(0.0, -50.0),
+            5.0,
+            Vec2::new(0.0, 100.0),
+            Vec2::ZERO,
+            Vec2::new(20.0, 10.0),
+        )
+        .expect("the ball moves straight into the box");
+        assert_close(contact.time, 0.35);
+        assert_close_vec(contact.normal, Vec2::NEG_Y);
+    }
+
+    #[test]
+    fn circle_hits_side_face() {
+        let contact = sweep_circle(
+            Vec2::new(50.0, 5.0),
+            5.0,
+            Vec2::new(-50.0, 0.0),
+            Vec2::ZERO,
+            Vec2::new(20.0, 10.0),
+        )
+        .expect("the ball moves straight into the box");
+        assert_close(contact.time, 0.5);
+        assert_close_vec(contact.normal, Vec2::X);
+    }
+
+    #[test]
+    fn circle_misses_box_beside_its_path() {
+        let contact = sweep_circle(
+            Vec2::new(-50.0, 20.0),
+            5.0,
+            Vec2::new(100.0, 0.0),
+            Vec2::ZERO,
+            Vec2::new(20.0, 10.0),
+        );
+        assert!(contact.is_none());
+    }
+
+    #[test]
+    fn circle_hits_corner() {
+        // at y = -14 the ball passes below the bottom side of the box, and only touches its corner (-10, -10),
+        // when its center is at x = -13 (3 to the side and 4 below the corner, 5 away)
+        let contact = sweep_circle(
+            Vec2::new(-50.0, -14.0),
+            5.0,
+            Vec2::new(100.0, 0.0),
+            Vec2::ZERO,
+            Vec2::new(10.0, 10.0),
+        )
+        .expect("the ball clips the corner");
+        assert_close(contact.time, 0.37);
+        assert_close_vec(contact.normal, Vec2::new(-0.6, -0.8));
+    }
+
+    #[test]
+    fn circle_misses_corner_that_the_square_would_hit() {
+        // the path goes through the corner of the grown box, but stays more than 5 away from the corner of the box
+        let contact = sweep_circle(
+            Vec2::new(-34.5, 5.5),
+            5.0,
+            Vec2::new(40.0, -40.0),
+            Vec2::ZERO,
+            Vec2::new(10.0, 10.0),
+        );
+        assert!(contact.is_none());
+    }
+
+    #[test]
+    fn circle_starting_inside_touches_at_once() {
+        // the ball starts 2 deep into the bottom of the grown box and keeps going up
+        let contact = sweep_circle(
+            Vec2::new(0.0, -13.0),
+            5.0,
+            Vec2::new(0.0, 10.0),
+            Vec2::ZERO,
+            Vec2::new(20.0, 10.0),
+        )
+        .expect("the ball moves further into the box");
+        assert_close(contact.time, 0.0);
+        assert_close_vec(contact.normal, Vec2::NEG_Y);
+    }
+
+    #[test]
+    fn circle_starting_inside_and_leaving_does_not_touch() {
+        let contact = sweep_circle(
+            Vec2::new(0.0, -13.0),
+            5.0,
+            Vec2::new(0.0, -10.0),
+            Vec2::ZERO,
+            Vec2::new(20.0, 10.0),
+        );
+        assert!(contact.is_none());
+    }
+
+    #[test]
+    fn circle_starting_inside_corner_touches_at_once() {
+        // 3 to the side and 3 below the corner (-10, -10), less than 5 away, moving towards the box
+        let contact = sweep_circle(
+            Vec2::new(-13.0, -13.0),
+            5.0,
+            Vec2::new(1.0, 1.0),
+            Vec2::ZERO,
+            Vec2::new(10.0, 10.0),
+        )
+        .expect("the ball moves further into the corner");
+        assert_close(contact.time, 0.0);
+        assert_close_vec(contact.normal, Vec2::new(-1.0, -1.0).normalize());
+    }
+
+    #[test]
+    fn fast_circle_does_not_tunnel_through_thin_box() {
+        // the ball ends the motion far past a box only 4 thick, but still hits it on the way
+        let contact = sweep_circle(
+            Vec2::new(0.0, -100.0),
+            5.0,
+            Vec2::new(0.0, 1000.0),
+            Vec2::ZERO,
+            Vec2::new(50.0, 2.0),
+        )
+        .expect("the ball passes through the box during the motion");
+        assert_close(contact.time, 0.093);
+        assert_close_vec(contact.normal, Vec2::NEG_Y);
+    }
+
+    #[test]
+    fn aabb_hits_face() {
+        let contact = sweep_aabb(
+            Vec2::new(-50.0, 0.0),
+            Vec2::splat(5.0),
+            Vec2::new(100.0, 0.0),
+            Vec2::ZERO,
+            Vec2::new(10.0, 10.0),
+        )
+        .expect("the box moves straight into the other one");
+        assert_close(contact.time, 0.35);
+        assert_close_vec(contact.normal, Vec2::NEG_X);
+    }
+
+    #[test]
+    fn aabb_hits_corner_on_the_later_axis() {
+        // the grown box spans -15..15. The point enters it on x at time 0.25 and on y at time 0.5,
+        // so it touches the bottom side, the axis it enters last
+        let contact = sweep_aabb(
+            Vec2::new(-40.0, -40.0),
+            Vec2::splat(5.0),
+            Vec2::new(100.0, 50.0),
+            Vec2::ZERO,
+            Vec2::new(10.0, 10.0),
+        )
+        .expect("the box moves into the corner of the other one");
+        assert_close(contact.time, 0.5);
+        assert_close_vec(contact.normal, Vec2::NEG_Y);
+    }
+
+    #[test]
+    fn aabb_starting_inside_goes_out_through_nearest_side() {
+        // 3 deep behind the right side of the grown box, 12 behind the top and bottom
+        let contact = sweep_aabb(
+            Vec2::new(12.0, 3.0),
+            Vec2::splat(5.0),
+            Vec2::new(-1.0, 0.0),
+            Vec2::ZERO,
+            Vec2::new(10.0, 10.0),
+        )
+        .expect("the box moves further into the other one");
+        assert_close(contact.time, 0.0);
+        assert_close_vec(contact.normal, Vec2::X);
+    }
+
+    #[test]
+    fn aabb_starting_inside_without_moving_does_not_touch() {
+        let contact = sweep_aabb(
+            Vec2::ZERO,
+            Vec2::splat(5.0),
+            Vec2::ZERO,
+            Vec2::ZERO,
+            Vec2::new(10.0, 10.0),
+        );
+        assert!(contact.is_none());
+    }
+
+    #[test]
+    fn aabb_behind_the_motion_is_not_hit() {
+        let contact = sweep_aabb(
+            Vec2::new(50.0, 0.0),
+            Vec2::splat(5.0),
+            Vec2::new(100.0, 0.0),
+            Vec2::ZERO,
+            Vec2::new(10.0, 10.0),
+        );
+        assert!(contact.is_none());
+    }
+
+    #[test]
+    fn penetration_pushes_circle_out_of_face() {
+        // the ball reaches 2 into the bottom of the box
+        let push = circle_penetration(
+            Vec2::new(0.0, -13.0),
+            5.0,
+            Vec2::ZERO,
+            Vec2::new(20.0, 10.0),
+        )
+        .expect("the ball overlaps the box");
+        assert_close_vec(push, Vec2::new(0.0, -2.0));
+    }
+
+    #[test]
+    fn penetration_pushes_circle_away_from_corner() {
+        // 3 to the side and 3 below the corner (-10, -10)
+        let push = circle_penetration(
+            Vec2::new(-13.0, -13.0),
+            5.0,
+            Vec2::ZERO,
+            Vec2::new(10.0, 10.0),
+        )
+        .expect("the ball overlaps the corner");
+        let distance = 18.0_f32.sqrt();
+        assert_close_vec(push, Vec2::new(-1.0, -1.0).normalize() * (5.0 - distance));
+    }
+
+    #[test]
+    fn penetration_pushes_center_inside_out_through_nearest_side() {
+        // the center is inside the box, 2 from its right side
+        let push = circle_penetration(Vec2::new(8.0, 1.0), 5.0, Vec2::ZERO, Vec2::new(10.0, 10.0))
+            .expect("the ball is inside the box");
+        assert_close_vec(push, Vec2::new(7.0, 0.0));
+    }
+
+    #[test]
+    fn penetration_is_none_when_only_touching() {
+        let push = circle_penetration(
+            Vec2::new(0.0, -15.0),
+            5.0,
+            Vec2::ZERO,
+            Vec2::new(20.0, 10.0),
+        );
+        assert!(push.is_none());
+    }
+}
@@ -0,0 +1,589 @@
+use std::fmt;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::powerup::WIDE_PADDLE_SCALE;
+
+// ** Game config **
+// The sizes, speeds and colors of the game are read from config/game.ron when the game starts, so they can be
+// tuned without recompiling. Anything left out of the file keeps its default, and a broken file is reported
+// and replaced by the defaults. Angles are written in degrees in the file and kept in radians in GameConfig.
+// See config/game.ron for every value and what it does.
+
+pub const GAME_CONFIG_PATH: &str = "config/game.ron";
+
+// the checked config, used by the game. The names match the constants they replaced
+#[derive(Resource, Clone, Debug)]
+pub struct GameConfig {
+    pub paddle_size: Vec2,
+    // y of the middle of the paddle
+    pub paddle_start_y: f32,
+    pub paddle_color: Color,
+    pub paddle_speed: f32,
+    pub paddle_momentum: bool,
+    pub paddle_acceleration: f32,
+    pub paddle_friction: f32,
+    pub paddle_max_bounce_angle: f32,
+
+    pub ball_size: Vec2,
+    pub ball_speed: f32,
+    pub ball_launch_min_angle: f32,
+    pub ball_launch_max_angle: f32,
+    pub ball_spin_per_paddle_speed: f32,
+    pub ball_max_spin: f32,
+    pub ball_spin_decay: f32,
+    pub ball_stall_angle: f32,
+    pub ball_stall_time: f32,
+    pub ball_stall_nudge_angle: f32,
+
+    // how the balls get faster during a life, see ball_speed.rs
+    pub ball_max_speed: f32,
+    pub speed_up_paddle_hits: usize,
+    pub speed_up_paddle_step: f32,
+    pub speed_up_upper_rows: usize,
+    pub speed_up_upper_rows_step: f32,
+    pub speed_up_interval: f32,
+    pub speed_up_interval_step: f32,
+
+    pub left_wall: f32,
+    pub right_wall: f32,
+    pub bottom_wall: f32,
+    pub top_wall: f32,
+    pub wall_thickness: f32,
+    pub wall_color: Color,
+    pub background_color: Color,
+
+    pub brick_size: Vec2,
+    // the color of bricks whose level file doesn't give one
+    pub brick_color: Color,
+    pub gap_between_bricks: f32,
+    pub gap_between_bricks_and_ceiling: f32,
+    pub gap_between_bricks_and_sides: f32,
+    pub gap_between_paddle_and_bricks: f32,
+
+    pub starting_lives: usize,
+    // the seed of GameRng when --seed isn't given, see rng.rs. It is only read when the game starts
+    pub seed: Option<u64>,
+}
+
+impl GameConfig {
+    // resting on the middle of the paddle, where the ball waits to be served
+    pub fn ball_starting_position(&self) -> Vec3 {
+        Vec3::new(
+            0.0,
+            self.paddle_start_y + (self.paddle_size.y + self.ball_size.y) * 0.5,
+            1.0,
+        )
+    }
+
+    // how many rows and columns of bricks fit between the walls, the ceiling and the paddle
+    pub fn brick_grid_size(&self) -> (usize, usize) {
+        let bricks_total_width =
+            (self.right_wall - self.left_wall) - 2. * self.gap_between_bricks_and_sides;
+        let bricks_total_height = (self.top_wall - self.bottom_wall)
+            - self.gap_between_bricks_and_ceiling
+            - self.gap_between_paddle_and_bricks;
+
+        // floor() rounds down the result to the nearest whole number, max(0.0) stops a negative room from wrapping around
+        let rows = (bricks_total_height / (self.brick_size.y + self.gap_between_bricks))
+            .floor()
+            .max(0.0) as usize;
+        let columns = (bricks_total_width / (self.brick_size.x + self.gap_between_bricks))
+            .floor()
+            .max(0.0) as usize;
+        (rows, columns)
+    }
+
+    // center of the brick in the given row (counted from the ceiling) and column (counted from the left wall)
+    pub fn brick_position(&self, row: usize, column: usize) -> Vec2 {
+        let offset_x = self.left_wall + self.gap_between_bricks_and_sides + self.brick_size.x * 0.5;
+        let offset_y =
+            self.top_wall - self.gap_between_bricks_and_ceiling - self.brick_size.y * 0.5;
+
+        Vec2::new(
+            // column as f32: This converts the column number (which is an integer) to a floating-point number
+            offset_x + column as f32 * (self.brick_size.x + self.gap_between_bricks),
+            offset_y - row as f32 * (self.brick_size.y + self.gap_between_bricks),
+        )
+    }
+
+    // checks every value and turns the file into the config the game uses, or explains which value is wrong
+    fn from_file(file: GameConfigFile) -> Result<Self, ConfigError> {
+        let GameConfigFile {
+            paddle,
+            ball,
+            speed_up,
+            arena,
+            bricks,
+            starting_lives,
+            seed,
+        } = file;
+
+        for (name, value) in [
+            ("paddle.size", paddle.size.0),
+            ("paddle.size", paddle.size.1),
+            ("paddle.speed", paddle.speed),
+            ("paddle.acceleration", paddle.acceleration),
+            ("paddle.friction", paddle.friction),
+            ("ball.size", ball.size),
+            ("ball.speed", ball.speed),
+            ("ball.stall_time", ball.stall_time),
+            ("speed_up.max_speed", speed_up.max_speed),
+            ("speed_up.interval", speed_up.interval),
+            ("arena.wall_thickness", arena.wall_thickness),
+            ("bricks.size", bricks.size.0),
+            ("bricks.size", bricks.size.1),
+        ] {
+            if value <= 0.0 {
+                return Err(ConfigError::NotPositive { name, value });
+            }
+        }
+        for (name, value) in [
+            ("paddle.distance_from_bottom", paddle.distance_from_bottom),
+            ("ball.spin_per_paddle_speed", ball.spin_per_paddle_speed),
+            ("ball.max_spin", ball.max_spin),
+            ("ball.spin_decay", ball.spin_decay),
+            ("speed_up.paddle_step", speed_up.paddle_step),
+            ("speed_up.upper_rows_step", speed_up.upper_rows_step),
+            ("speed_up.interval_step", speed_up.interval_step),
+            ("bricks.gap", bricks.gap),
+            ("bricks.gap_to_ceiling", bricks.gap_to_ceiling),
+            ("bricks.gap_to_sides", bricks.gap_to_sides),
+            ("bricks.gap_to_paddle", bricks.gap_to_paddle),
+        ] {
+            if value < 0.0 {
+                return Err(ConfigError::Negative { name, value });
+            }
+        }
+        // the angles are measured from straight up (or from the axis for the stall angles), 90 degrees would be flat
+        for (name, value, max) in [
+            ("paddle.max_bounce_angle", paddle.max_bounce_angle, 90.0),
+            ("ball.launch_min_angle", ball.launch_min_angle, 90.0),
+            ("ball.launch_max_angle", ball.launch_max_angle, 90.0),
+            ("ball.stall_angle", ball.stall_angle, 45.0),
+            ("ball.stall_nudge_angle", ball.stall_nudge_angle, 45.0),
+        ] {
+            if value <= 0.0 || value >= max {
+                return Err(ConfigError::InvalidAngle { name, value, max });
+            }
+        }
+        if ball.launch_min_angle >= ball.launch_max_angle {
+            return Err(ConfigError::AnglesOutOfOrder {
+                smaller: "ball.launch_min_angle",
+                larger: "ball.launch_max_angle",
+            });
+        }
+        // a nudged ball must end up outside the stall angle, or it would keep being nudged
+        if ball.stall_angle >= ball.stall_nudge_angle {
+            return Err(ConfigError::AnglesOutOfOrder {
+                smaller: "ball.stall_angle",
+                larger: "ball.stall_nudge_angle",
+            });
+        }
+        // the speed a life starts at can't already be over the limit (levels are checked against it too, see level.rs)
+        if speed_up.max_speed < ball.speed {
+            return Err(ConfigError::MaxSpeedTooLow {
+                max: speed_up.max_speed,
+                speed: ball.speed,
+            });
+        }
+        for (name, count) in [
+            ("speed_up.paddle_hits", speed_up.paddle_hits),
+            ("speed_up.upper_rows", speed_up.upper_rows),
+        ] {
+            if count == 0 {
+                return Err(ConfigError::ZeroCount { name });
+            }
+        }
+        for (name, color) in [
+            ("paddle.color", paddle.color),
+            ("arena.wall_color", arena.wall_color),
+            ("arena.background_color", arena.background_color),
+            ("bricks.color", bricks.color),
+        ] {
+            if ![color.0, color.1, color.2]
+                .iter()
+                .all(|part| (0.0..=1.0).contains(part))
+            {
+                return Err(ConfigError::InvalidColor { name });
+            }
+        }
+        if arena.left >= arena.right || arena.bottom >= arena.top {
+            return Err(ConfigError::EmptyArena);
+        }
+        let room_for_paddle = arena.right - arena.left - arena.wall_thickness;
+        if paddle.size.0 >= room_for_paddle {
+            return Err(ConfigError::PaddleTooWide {
+                width: paddle.size.0,
+                room: room_for_paddle,
+            });
+        }
+        // the WidePaddle power-up has to fit between the walls too
+        let wide_paddle_width = paddle.size.0 * WIDE_PADDLE_SCALE;
+        if wide_paddle_width >= room_for_paddle {
+            return Err(ConfigError::WidePaddleTooWide {
+                width: wide_paddle_width,
+                room: room_for_paddle,
+            });
+        }
+        if arena.bottom + paddle.distance_from_bottom >= arena.top {
+            return Err(ConfigError::PaddleAboveCeiling);
+        }
+        if starting_lives == 0 {
+            return Err(ConfigError::NoLives);
+        }
+
+        let rgb = |(r, g, b): (f32, f32, f32)| Color::rgb(r, g, b);
+        let config = GameConfig {
+            paddle_size: Vec2::new(paddle.size.0, paddle.size.1),
+            paddle_start_y: arena.bottom + paddle.distance_from_bottom,
+            paddle_color: rgb(paddle.color),
+            paddle_speed: paddle.speed,
+            paddle_momentum: paddle.momentum,
+            paddle_acceleration: paddle.acceleration,
+            paddle_friction: paddle.friction,
+            paddle_max_bounce_angle: paddle.max_bounce_angle.to_radians(),
+
+            ball_size: Vec2::splat(ball.size),
+            ball_speed: ball.speed,
+            ball_launch_min_angle: ball.launch_min_angle.to_radians(),
+            ball_launch_max_angle: ball.launch_max_angle.to_radians(),
+            ball_spin_per_paddle_speed: ball.spin_per_paddle_speed,
+            ball_max_spin: ball.max_spin,
+            ball_spin_decay: ball.spin_decay,
+            ball_stall_angle: ball.stall_angle.to_radians(),
+            ball_stall_time: ball.stall_time,
+            ball_stall_nudge_angle: ball.stall_nudge_angle.to_radians(),
+
+            ball_max_speed: speed_up.max_speed,
+            speed_up_paddle_hits: speed_up.paddle_hits,
+            speed_up_paddle_step: speed_up.paddle_step,
+            speed_up_upper_rows: speed_up.upper_rows,
+            speed_up_upper_rows_step: speed_up.upper_rows_step,
+            speed_up_interval: speed_up.interval,
+            speed_up_interval_step: speed_up.interval_step,
+
+            left_wall: arena.left,
+            right_wall: arena.right,
+            bottom_wall: arena.bottom,
+            top_wall: arena.top,
+            wall_thickness: arena.wall_thickness,
+            wall_color: rgb(arena.wall_color),
+            background_color: rgb(arena.background_color),
+
+            brick_size: Vec2::new(bricks.size.0, bricks.size.1),
+            brick_color: rgb(bricks.color),
+            gap_between_bricks: bricks.gap,
+            gap_between_bricks_and_ceiling: bricks.gap_to_ceiling,
+            gap_between_bricks_and_sides: bricks.gap_to_sides,
+            gap_between_paddle_and_bricks: bricks.gap_to_paddle,
+
+            starting_lives,
+            seed,
+        };
+        // every level needs at least one spot for a brick
+        let (rows, columns) = config.brick_grid_size();
+        if rows == 0 || columns == 0 {
+            return Err(ConfigError::NoRoomForBricks);
+        }
+        Ok(config)
+    }
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig::from_file(GameConfigFile::default()).expect("the default config is valid")
+    }
+}
+
+// config/game.ron as serde reads it, GameConfig::from_file checks the values.
+// deny_unknown_fields reports a misspelled name instead of silently using the default
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct GameConfigFile {
+    paddle: PaddleFile,
+    ball: BallFile,
+    speed_up: SpeedUpFile,
+    arena: ArenaFile,
+    bricks: BricksFile,
+    starting_lives: usize,
+    seed: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PaddleFile {
+    size: (f32, f32),
+    distance_from_bottom: f32,
+    color: (f32, f32, f32),
+    speed: f32,
+    momentum: bool,
+    acceleration: f32,
+    friction: f32,
+    max_bounce_angle: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct BallFile {
+    size: f32,
+    speed: f32,
+    launch_min_angle: f32,
+    launch_max_angle: f32,
+    spin_per_paddle_speed: f32,
+    max_spin: f32,
+    spin_decay: f32,
+    stall_angle: f32,
+    stall_time: f32,
+    stall_nudge_angle: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SpeedUpFile {
+    max_speed: f32,
+    paddle_hits: usize,
+    paddle_step: f32,
+    upper_rows: usize,
+    upper_rows_step: f32,
+    interval: f32,
+    interval_step: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ArenaFile {
+    left: f32,
+    right: f32,
+    bottom: f32,
+    top: f32,
+    wall_thickness: f32,
+    wall_color: (f32, f32, f32),
+    background_color: (f32, f32, f32),
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct BricksFile {
+    size: (f32, f32),
+    color: (f32, f32, f32),
+    gap: f32,
+    gap_to_ceiling: f32,
+    gap_to_sides: f32,
+    gap_to_paddle: f32,
+}
+
+// the defaults, used for anything the file leaves out
+impl Default for GameConfigFile {
+    fn default() -> Self {
+        GameConfigFile {
+            paddle: PaddleFile::default(),
+            ball: BallFile::default(),
+            speed_up: SpeedUpFile::default(),
+            arena: ArenaFile::default(),
+            bricks: BricksFile::default(),
+            starting_lives: 3,
+            seed: None,
+        }
+    }
+}
+
+impl Default for PaddleFile {
+    fn default() -> Self {
+        PaddleFile {
+            size: (120.0, 20.0),
+            distance_from_bottom: 60.0,
+            color: (0.3, 0.3, 0.7),
+            speed: 500.0,
+            momentum: true,
+            acceleration: 3000.0,
+            friction: 2500.0,
+            max_bounce_angle: 60.0,
+        }
+    }
+}
+
+impl Default for BallFile {
+    fn default() -> Self {
+        BallFile {
+            size: 30.0,
+            speed: 400.0,
+            launch_min_angle: 15.0,
+            launch_max_angle: 60.0,
+            spin_per_paddle_speed: 0.002,
+            max_spin: 1.0,
+            spin_decay: 0.8,
+            stall_angle: 5.0,
+            stall_time: 3.0,
+            stall_nudge_angle: 15.0,
+        }
+    }
+}
+
+impl Default for SpeedUpFile {
+    fn default() -> Self {
+        SpeedUpFile {
+            max_speed: 900.0,
+            paddle_hits: 5,
+            paddle_step: 20.0,
+            upper_rows: 2,
+            upper_rows_step: 60.0,
+            interval: 10.0,
+            interval_step: 10.0,
+        }
+    }
+}
+
+impl Default for ArenaFile {
+    fn default() -> Self {
+        ArenaFile {
+            left: -450.0,
+            right: 450.0,
+            bottom: -300.0,
+            top: 300.0,
+            wall_thickness: 10.0,
+            wall_color: (0.8, 0.8, 0.8),
+            background_color: (0.9, 0.9, 0.9),
+        }
+    }
+}
+
+impl Default for BricksFile {
+    fn default() -> Self {
+        BricksFile {
+            size: (100.0, 30.0),
+            color: (0.5, 0.5, 1.0),
+            gap: 5.0,
+            gap_to_ceiling: 20.0,
+            gap_to_sides: 20.0,
+            gap_to_paddle: 270.0,
+        }
+    }
+}
+
+// why config/game.ron couldn't be used
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(std::io::Error),
+    Parse(ron::error::SpannedError),
+    NotPositive {
+        name: &'static str,
+        value: f32,
+    },
+    Negative {
+        name: &'static str,
+        value: f32,
+    },
+    InvalidAngle {
+        name: &'static str,
+        value: f32,
+        max: f32,
+    },
+    AnglesOutOfOrder {
+        smaller: &'static str,
+        larger: &'static str,
+    },
+    InvalidColor {
+        name: &'static str,
+    },
+    EmptyArena,
+    PaddleTooWide {
+        width: f32,
+        room: f32,
+    },
+    WidePaddleTooWide {
+        width: f32,
+        room: f32,
+    },
+    PaddleAboveCeiling,
+    NoRoomForBricks,
+    MaxSpeedTooLow {
+        max: f32,
+        speed: f32,
+    },
+    ZeroCount {
+        name: &'static str,
+    },
+    NoLives,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Read(err) => write!(f, "could not read the file: {err}"),
+            ConfigError::Parse(err) => write!(f, "could not read the config: {err}"),
+            ConfigError::NotPositive { name, value } => {
+                write!(f, "{name} must be greater than 0, found {value}")
+            }
+            ConfigError::Negative { name, value } => {
+                write!(f, "{name} can't be negative, found {value}")
+            }
+            ConfigError::InvalidAngle { name, value, max } => write!(
+                f,
+                "{name} must be between 0 and {max} degrees (both excluded), found {value}"
+            ),
+            ConfigError::AnglesOutOfOrder { smaller, larger } => {
+                write!(f, "{smaller} must be smaller than {larger}")
+            }
+            ConfigError::InvalidColor { name } => write!(
+                f,
+                "{name} must have red, green and blue between 0.0 and 1.0"
+            ),
+            ConfigError::EmptyArena => write!(
+                f,
+                "arena.left must be smaller than arena.right and arena.bottom smaller than arena.top"
+            ),
+            ConfigError::PaddleTooWide { width, room } => write!(
+                f,
+                "the paddle is {width} wide but there is only {room} between the walls"
+            ),
+            ConfigError::WidePaddleTooWide { width, room } => write!(
+                f,
+                "the paddle is {width} wide with the WidePaddle power-up but there is only {room} between the walls"
+            ),
+            ConfigError::PaddleAboveCeiling => write!(
+                f,
+                "paddle.distance_from_bottom puts the paddle above the top of the arena"
+            ),
+            ConfigError::NoRoomForBricks => write!(
+                f,
+                "not a single brick fits between the walls, the ceiling and the paddle"
+            ),
+            ConfigError::MaxSpeedTooLow { max, speed } => write!(
+                f,
+                "speed_up.max_speed ({max}) can't be lower than ball.speed ({speed})"
+            ),
+            ConfigError::ZeroCount { name } => write!(f, "{name} must be at least 1"),
+            ConfigError::NoLives => write!(f, "starting_lives must be at least 1"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<ron::error::SpannedError> for ConfigError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        ConfigError::Parse(err)
+    }
+}
+
+// reads config/game.ron. A missing file means the defaults, a broken one is reported and the defaults are used
+pub fn load_game_config() -> GameConfig {
+    match read_config_file() {
+        Ok(config) => config,
+        Err(ConfigError::Read(err)) if err.kind() == std::io::ErrorKind::NotFound => {
+            info!("no {GAME_CONFIG_PATH}, using the default config");
+            GameConfig::default()
+        }
+        Err(err) => {
+            warn!("invalid config file {GAME_CONFIG_PATH}: {err}, using the default config");
+            GameConfig::default()
+        }
+    }
+}
+
+pub fn read_config_file() -> Result<GameConfig, ConfigError> {
+    let text = std::fs::read_to_string(GAME_CONFIG_PATH).map_err(ConfigError::Read)?;
+    GameConfig::from_file(ron::from_str(&text)?)
+}
@@ -0,0 +1,708 @@
+use std::fmt;
+
+use bevy::{prelude::*, window::PrimaryWindow};
+use serde::Deserialize;
+
+// ** Controls **
+// The game doesn't look at keys, buttons or the mouse directly. read_input_actions turns them into InputActions
+// (move left/right, move the paddle to a position, launch, pause, moving through menus, continuing the saved run,
+// going back to the main menu, rebuilding an edited level and changing the volume) once per frame,
+// and the game systems read those.
+// Which keys and buttons do what is set in config/input.ron, for example:
+//
+// (
+//     move_left: ["A", "Left"],
+//     move_right: ["D", "Right"],
+//     launch: ["Space"],
+//     pause: ["P", "Escape"],
+//     menu_up: ["Up", "W"],
+//     menu_down: ["Down", "S"],
+//     menu_confirm: ["Return"],
+//     menu_back: ["Escape"],
+//     continue_run: ["C"],
+//     main_menu: ["M"],
+//     rebuild_level: ["F5"],
+//     toggle_mute: ["Key0"],
+//     master_volume_down: ["Minus"],
+//     master_volume_up: ["Equals"],
+//     sfx_volume_down: ["BracketLeft"],
+//     sfx_volume_up: ["BracketRight"],
+//     mouse_launch: ["Left"],
+//     gamepad_move_left: ["DPadLeft"],
+//     gamepad_move_right: ["DPadRight"],
+//     gamepad_launch: ["South"],
+//     gamepad_pause: ["Start"],
+//     gamepad_menu_up: ["DPadUp"],
+//     gamepad_menu_down: ["DPadDown"],
+//     gamepad_menu_confirm: ["South"],
+//     gamepad_menu_back: ["East"],
+//     gamepad_continue_run: ["West"],
+//     gamepad_main_menu: ["North"],
+//     gamepad_rebuild_level: ["Select"],
+//     gamepad_toggle_mute: [],
+//     gamepad_master_volume_down: [],
+//     gamepad_master_volume_up: [],
+//     gamepad_sfx_volume_down: [],
+//     gamepad_sfx_volume_up: [],
+//     mouse_paddle: true,
+//     stick_dead_zone: 0.2,
+// )
+//
+// Anything left out of the file keeps its default (the bindings above). Keyboard, mouse and gamepads all work at the
+// same time. The left stick of a gamepad moves the paddle too, unless it is pushed less than stick_dead_zone.
+// In menus, the move left/right bindings change the selected setting.
+
+const INPUT_BINDINGS_PATH: &str = "config/input.ron";
+
+// what the player wants to do this frame, whatever they pressed to do it
+#[derive(Resource, Default)]
+pub struct InputActions {
+    // -1.0 is full speed to the left, 1.0 full speed to the right
+    pub movement: f32,
+    // where the mouse wants the paddle to go (x in the game world), until a key or the stick moves it
+    pub paddle_target: Option<f32>,
+    // true only in the frame the launch key/button went down
+    pub launch_pressed: bool,
+    // true for as long as the launch key/button is held (the laser keeps firing)
+    pub launch_held: bool,
+    pub pause_pressed: bool,
+    // menu actions are only true in the frame their key/button went down
+    pub menu_up: bool,
+    pub menu_down: bool,
+    pub menu_left: bool,
+    pub menu_right: bool,
+    pub menu_confirm: bool,
+    pub menu_back: bool,
+    // continues the saved run from the main menu
+    pub continue_run: bool,
+    // goes back to the main menu from the game over screen
+    pub main_menu: bool,
+    // rebuilds the bricks of a level whose file changed, see hot_reload.rs
+    pub rebuild_level: bool,
+    // mute the sound and change the master and sound effects volumes, see audio.rs
+    pub toggle_mute: bool,
+    pub master_volume_down: bool,
+    pub master_volume_up: bool,
+    pub sfx_volume_down: bool,
+    pub sfx_volume_up: bool,
+}
+
+// which keys, mouse buttons and gamepad buttons are bound to each action
+#[derive(Resource)]
+pub struct InputBindings {
+    move_left: Vec<KeyCode>,
+    move_right: Vec<KeyCode>,
+    launch: Vec<KeyCode>,
+    pause: Vec<KeyCode>,
+    menu_up: Vec<KeyCode>,
+    menu_down: Vec<KeyCode>,
+    menu_confirm: Vec<KeyCode>,
+    menu_back: Vec<KeyCode>,
+    continue_run: Vec<KeyCode>,
+    main_menu: Vec<KeyCode>,
+    rebuild_level: Vec<KeyCode>,
+    toggle_mute: Vec<KeyCode>,
+    master_volume_down: Vec<KeyCode>,
+    master_volume_up: Vec<KeyCode>,
+    sfx_volume_down: Vec<KeyCode>,
+    sfx_volume_up: Vec<KeyCode>,
+    mouse_launch: Vec<MouseButton>,
+    gamepad_move_left: Vec<GamepadButtonType>,
+    gamepad_move_right: Vec<GamepadButtonType>,
+    gamepad_launch: Vec<GamepadButtonType>,
+    gamepad_pause: Vec<GamepadButtonType>,
+    gamepad_menu_up: Vec<GamepadButtonType>,
+    gamepad_menu_down: Vec<GamepadButtonType>,
+    gamepad_menu_confirm: Vec<GamepadButtonType>,
+    gamepad_menu_back: Vec<GamepadButtonType>,
+    gamepad_continue_run: Vec<GamepadButtonType>,
+    gamepad_main_menu: Vec<GamepadButtonType>,
+    gamepad_rebuild_level: Vec<GamepadButtonType>,
+    gamepad_toggle_mute: Vec<GamepadButtonType>,
+    gamepad_master_volume_down: Vec<GamepadButtonType>,
+    gamepad_master_volume_up: Vec<GamepadButtonType>,
+    gamepad_sfx_volume_down: Vec<GamepadButtonType>,
+    gamepad_sfx_volume_up: Vec<GamepadButtonType>,
+    mouse_paddle: bool,
+    stick_dead_zone: f32,
+}
+
+// config/input.ron with the keys and buttons still as names, InputBindings::from_file looks them up.
+// A misspelled action name is reported instead of being ignored
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct InputBindingsFile {
+    move_left: Vec<String>,
+    move_right: Vec<String>,
+    launch: Vec<String>,
+    pause: Vec<String>,
+    menu_up: Vec<String>,
+    menu_down: Vec<String>,
+    menu_confirm: Vec<String>,
+    menu_back: Vec<String>,
+    continue_run: Vec<String>,
+    main_menu: Vec<String>,
+    rebuild_level: Vec<String>,
+    toggle_mute: Vec<String>,
+    master_volume_down: Vec<String>,
+    master_volume_up: Vec<String>,
+    sfx_volume_down: Vec<String>,
+    sfx_volume_up: Vec<String>,
+    mouse_launch: Vec<String>,
+    gamepad_move_left: Vec<String>,
+    gamepad_move_right: Vec<String>,
+    gamepad_launch: Vec<String>,
+    gamepad_pause: Vec<String>,
+    gamepad_menu_up: Vec<String>,
+    gamepad_menu_down: Vec<String>,
+    gamepad_menu_confirm: Vec<String>,
+    gamepad_menu_back: Vec<String>,
+    gamepad_continue_run: Vec<String>,
+    gamepad_main_menu: Vec<String>,
+    gamepad_rebuild_level: Vec<String>,
+    gamepad_toggle_mute: Vec<String>,
+    gamepad_master_volume_down: Vec<String>,
+    gamepad_master_volume_up: Vec<String>,
+    gamepad_sfx_volume_down: Vec<String>,
+    gamepad_sfx_volume_up: Vec<String>,
+    // moving the mouse moves the paddle to the cursor
+    mouse_paddle: bool,
+    stick_dead_zone: f32,
+}
+
+impl Default for InputBindingsFile {
+    fn default() -> Self {
+        // turns a list of &str into a Vec<String>
+        let names =
+            |names: &[&str]| -> Vec<String> { names.iter().map(|name| name.to_string()).collect() };
+        InputBindingsFile {
+            move_left: names(&["A", "Left"]),
+            move_right: names(&["D", "Right"]),
+            launch: names(&["Space"]),
+            pause: names(&["P", "Escape"]),
+            menu_up: names(&["Up", "W"]),
+            menu_down: names(&["Down", "S"]),
+            menu_confirm: names(&["Return"]),
+            menu_back: names(&["Escape"]),
+            continue_run: names(&["C"]),
+            main_menu: names(&["M"]),
+            rebuild_level: names(&["F5"]),
+            toggle_mute: names(&["Key0"]),
+            master_volume_down: names(&["Minus"]),
+            master_volume_up: names(&["Equals"]),
+            sfx_volume_down: names(&["BracketLeft"]),
+            sfx_volume_up: names(&["BracketRight"]),
+            mouse_launch: names(&["Left"]),
+            gamepad_move_left: names(&["DPadLeft"]),
+            gamepad_move_right: names(&["DPadRight"]),
+            gamepad_launch: names(&["South"]),
+            gamepad_pause: names(&["Start"]),
+            gamepad_menu_up: names(&["DPadUp"]),
+            gamepad_menu_down: names(&["DPadDown"]),
+            gamepad_menu_confirm: names(&["South"]),
+            gamepad_menu_back: names(&["East"]),
+            gamepad_continue_run: names(&["West"]),
+            gamepad_main_menu: names(&["North"]),
+            gamepad_rebuild_level: names(&["Select"]),
+            gamepad_toggle_mute: Vec::new(),
+            gamepad_master_volume_down: Vec::new(),
+            gamepad_master_volume_up: Vec::new(),
+            gamepad_sfx_volume_down: Vec::new(),
+            gamepad_sfx_volume_up: Vec::new(),
+            mouse_paddle: true,
+            stick_dead_zone: 0.2,
+        }
+    }
+}
+
+// why config/input.ron couldn't be used
+#[derive(Debug)]
+enum BindingsError {
+    Read(std::io::Error),
+    Parse(ron::error::SpannedError),
+    UnknownKey(String),
+    UnknownMouseButton(String),
+    UnknownGamepadButton(String),
+    InvalidDeadZone(f32),
+}
+
+impl fmt::Display for BindingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BindingsError::Read(err) => write!(f, "could not read the file: {err}"),
+            BindingsError::Parse(err) => write!(f, "could not read the bindings: {err}"),
+            BindingsError::UnknownKey(name) => write!(f, "\"{name}\" is not a known key"),
+            BindingsError::UnknownMouseButton(name) => {
+                write!(f, "\"{name}\" is not a known mouse button")
+            }
+            BindingsError::UnknownGamepadButton(name) => {
+                write!(f, "\"{name}\" is not a known gamepad button")
+            }
+            BindingsError::InvalidDeadZone(dead_zone) => write!(
+                f,
+                "stick_dead_zone must be between 0.0 and 1.0, found {dead_zone}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BindingsError {}
+
+impl From<ron::error::SpannedError> for BindingsError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        BindingsError::Parse(err)
+    }
+}
+
+impl InputBindings {
+    // turns the names in the file into keys and buttons, or explains which one is wrong
+    fn from_file(file: InputBindingsFile) -> Result<Self, BindingsError> {
+        if !(0.0..1.0).contains(&file.stick_dead_zone) {
+            return Err(BindingsError::InvalidDeadZone(file.stick_dead_zone));
+        }
+        Ok(InputBindings {
+            move_left: parse_names(&file.move_left, key_code, BindingsError::UnknownKey)?,
+            move_right: parse_names(&file.move_right, key_code, BindingsError::UnknownKey)?,
+            launch: parse_names(&file.launch, key_code, BindingsError::UnknownKey)?,
+            pause: parse_names(&file.pause, key_code, BindingsError::UnknownKey)?,
+            menu_up: parse_names(&file.menu_up, key_code, BindingsError::UnknownKey)?,
+            menu_down: parse_names(&file.menu_down, key_code, BindingsError::UnknownKey)?,
+            menu_confirm: parse_names(&file.menu_confirm, key_code, BindingsError::UnknownKey)?,
+            menu_back: parse_names(&file.menu_back, key_code, BindingsError::UnknownKey)?,
+            continue_run: parse_names(&file.continue_run, key_code, BindingsError::UnknownKey)?,
+            main_menu: parse_names(&file.main_menu, key_code, BindingsError::UnknownKey)?,
+            rebuild_level: parse_names(&file.rebuild_level, key_code, BindingsError::UnknownKey)?,
+            toggle_mute: parse_names(&file.toggle_mute, key_code, BindingsError::UnknownKey)?,
+            master_volume_down: parse_names(
+                &file.master_volume_down,
+                key_code,
+                BindingsError::UnknownKey,
+            )?,
+            master_volume_up: parse_names(
+                &file.master_volume_up,
+                key_code,
+                BindingsError::UnknownKey,
+            )?,
+            sfx_volume_down: parse_names(
+                &file.sfx_volume_down,
+                key_code,
+                BindingsError::UnknownKey,
+            )?,
+            sfx_volume_up: parse_names(&file.sfx_volume_up, key_code, BindingsError::UnknownKey)?,
+            mouse_launch: parse_names(
+                &file.mouse_launch,
+                mouse_button,
+                BindingsError::UnknownMouseButton,
+            )?,
+            gamepad_move_left: parse_names(
+                &file.gamepad_move_left,
+                gamepad_button,
+                BindingsError::UnknownGamepadButton,
+            )?,
+            gamepad_move_right: parse_names(
+                &file.gamepad_move_right,
+                gamepad_button,
+                BindingsError::UnknownGamepadButton,
+            )?,
+            gamepad_launch: parse_names(
+                &file.gamepad_launch,
+                gamepad_button,
+                BindingsError::UnknownGamepadButton,
+            )?,
+            gamepad_pause: parse_names(
+                &file.gamepad_pause,
+                gamepad_button,
+                BindingsError::UnknownGamepadButton,
+            )?,
+            gamepad_menu_up: parse_names(
+                &file.gamepad_menu_up,
+                gamepad_button,
+                BindingsError::UnknownGamepadButton,
+            )?,
+            gamepad_menu_down: parse_names(
+                &file.gamepad_menu_down,
+                gamepad_button,
+                BindingsError::UnknownGamepadButton,
+            )?,
+            gamepad_menu_confirm: parse_names(
+                &file.gamepad_menu_confirm,
+                gamepad_button,
+                BindingsError::UnknownGamepadButton,
+            )?,
+            gamepad_menu_back: parse_names(
+                &file.gamepad_menu_back,
+                gamepad_button,
+                BindingsError::UnknownGamepadButton,
+            )?,
+            gamepad_continue_run: parse_names(
+                &file.gamepad_continue_run,
+                gamepad_button,
+                BindingsError::UnknownGamepadButton,
+            )?,
+            gamepad_main_menu: parse_names(
+                &file.gamepad_main_menu,
+                gamepad_button,
+                BindingsError::UnknownGamepadButton,
+            )?,
+            gamepad_rebuild_level: parse_names(
+                &file.gamepad_rebuild_level,
+                gamepad_button,
+                BindingsError::UnknownGamepadButton,
+            )?,
+            gamepad_toggle_mute: parse_names(
+                &file.gamepad_toggle_mute,
+                gamepad_button,
+                BindingsError::UnknownGamepadButton,
+            )?,
+            gamepad_master_volume_down: parse_names(
+                &file.gamepad_master_volume_down,
+                gamepad_button,
+                BindingsError::UnknownGamepadButton,
+            )?,
+            gamepad_master_volume_up: parse_names(
+                &file.gamepad_master_volume_up,
+                gamepad_button,
+                BindingsError::UnknownGamepadButton,
+            )?,
+            gamepad_sfx_volume_down: parse_names(
+                &file.gamepad_sfx_volume_down,
+                gamepad_button,
+                BindingsError::UnknownGamepadButton,
+            )?,
+            gamepad_sfx_volume_up: parse_names(
+                &file.gamepad_sfx_volume_up,
+                gamepad_button,
+                BindingsError::UnknownGamepadButton,
+            )?,
+            mouse_paddle: file.mouse_paddle,
+            stick_dead_zone: file.stick_dead_zone,
+        })
+    }
+}
+
+// the bindings that are named in the hints on screen
+#[derive(Clone, Copy)]
+pub enum Binding {
+    MoveLeft,
+    MoveRight,
+    Launch,
+    Pause,
+    MenuConfirm,
+    MenuBack,
+    ContinueRun,
+    MainMenu,
+    RebuildLevel,
+    ToggleMute,
+    MasterVolumeDown,
+    MasterVolumeUp,
+    SfxVolumeDown,
+    SfxVolumeUp,
+}
+
+impl InputBindings {
+    // the keys bound to an action, for the hints on screen, e.g. "P or Escape".
+    // The launch action also names its mouse buttons
+    pub fn names(&self, binding: Binding) -> String {
+        let keys = match binding {
+            Binding::MoveLeft => &self.move_left,
+            Binding::MoveRight => &self.move_right,
+            Binding::Launch => &self.launch,
+            Binding::Pause => &self.pause,
+            Binding::MenuConfirm => &self.menu_confirm,
+            Binding::MenuBack => &self.menu_back,
+            Binding::ContinueRun => &self.continue_run,
+            Binding::MainMenu => &self.main_menu,
+            Binding::RebuildLevel => &self.rebuild_level,
+            Binding::ToggleMute => &self.toggle_mute,
+            Binding::MasterVolumeDown => &self.master_volume_down,
+            Binding::MasterVolumeUp => &self.master_volume_up,
+            Binding::SfxVolumeDown => &self.sfx_volume_down,
+            Binding::SfxVolumeUp => &self.sfx_volume_up,
+        };
+        let mut names: Vec<String> = keys
+            .iter()
+            .filter_map(|&key| key_name(key))
+            .map(String::from)
+            .collect();
+        if let Binding::Launch = binding {
+            names.extend(
+                self.mouse_launch
+                    .iter()
+                    .map(|button| format!("the {button:?} mouse button").to_lowercase()),
+            );
+        }
+        if names.is_empty() {
+            return "(no key)".to_string();
+        }
+        names.join(" or ")
+    }
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        InputBindings::from_file(InputBindingsFile::default())
+            .expect("the default bindings only use known names")
+    }
+}
+
+// reads config/input.ron when the game starts, falling back to the default controls like load_game_config does
+pub fn load_input_bindings(mut commands: Commands) {
+    let bindings = match read_bindings_file() {
+        Ok(bindings) => bindings,
+        Err(BindingsError::Read(err)) if err.kind() == std::io::ErrorKind::NotFound => {
+            info!("no {INPUT_BINDINGS_PATH}, using the default controls");
+            InputBindings::default()
+        }
+        Err(err) => {
+            warn!("invalid bindings file {INPUT_BINDINGS_PATH}: {err}, using the default controls");
+            InputBindings::default()
+        }
+    };
+    commands.insert_resource(bindings);
+}
+
+fn read_bindings_file() -> Result<InputBindings, BindingsError> {
+    let text = std::fs::read_to_string(INPUT_BINDINGS_PATH).map_err(BindingsError::Read)?;
+    InputBindings::from_file(ron::from_str(&text)?)
+}
+
+// turns every name of the list into a key or button with `parse`, `error` builds the error for an unknown name
+fn parse_names<T>(
+    names: &[String],
+    parse: fn(&str) -> Option<T>,
+    error: fn(String) -> BindingsError,
+) -> Result<Vec<T>, BindingsError> {
+    names
+        .iter()
+        .map(|name| parse(name).ok_or_else(|| error(name.clone())))
+        .collect()
+}
+
+// the keys that can be used in the bindings file, by the name of their KeyCode
+const KEY_NAMES: &[(&str, KeyCode)] = &[
+    ("A", KeyCode::A),
+    ("B", KeyCode::B),
+    ("C", KeyCode::C),
+    ("D", KeyCode::D),
+    ("E", KeyCode::E),
+    ("F", KeyCode::F),
+    ("G", KeyCode::G),
+    ("H", KeyCode::H),
+    ("I", KeyCode::I),
+    ("J", KeyCode::J),
+    ("K", KeyCode::K),
+    ("L", KeyCode::L),
+    ("M", KeyCode::M),
+    ("N", KeyCode::N),
+    ("O", KeyCode::O),
+    ("P", KeyCode::P),
+    ("Q", KeyCode::Q),
+    ("R", KeyCode::R),
+    ("S", KeyCode::S),
+    ("T", KeyCode::T),
+    ("U", KeyCode::U),
+    ("V", KeyCode::V),
+    ("W", KeyCode::W),
+    ("X", KeyCode::X),
+    ("Y", KeyCode::Y),
+    ("Z", KeyCode::Z),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Space", KeyCode::Space),
+    ("Return", KeyCode::Return),
+    ("Escape", KeyCode::Escape),
+    ("Tab", KeyCode::Tab),
+    ("Back", KeyCode::Back),
+    ("Key0", KeyCode::Key0),
+    ("Key1", KeyCode::Key1),
+    ("Key2", KeyCode::Key2),
+    ("Key3", KeyCode::Key3),
+    ("Key4", KeyCode::Key4),
+    ("Key5", KeyCode::Key5),
+    ("Key6", KeyCode::Key6),
+    ("Key7", KeyCode::Key7),
+    ("Key8", KeyCode::Key8),
+    ("Key9", KeyCode::Key9),
+    ("Minus", KeyCode::Minus),
+    ("Equals", KeyCode::Equals),
+    ("BracketLeft", KeyCode::BracketLeft),
+    ("BracketRight", KeyCode::BracketRight),
+    ("F1", KeyCode::F1),
+    ("F2", KeyCode::F2),
+    ("F3", KeyCode::F3),
+    ("F4", KeyCode::F4),
+    ("F5", KeyCode::F5),
+    ("F6", KeyCode::F6),
+    ("F7", KeyCode::F7),
+    ("F8", KeyCode::F8),
+    ("F9", KeyCode::F9),
+    ("F10", KeyCode::F10),
+    ("F11", KeyCode::F11),
+    ("F12", KeyCode::F12),
+    ("LShift", KeyCode::ShiftLeft),
+    ("RShift", KeyCode::ShiftRight),
+    ("LControl", KeyCode::ControlLeft),
+    ("RControl", KeyCode::ControlRight),
+    ("LAlt", KeyCode::AltLeft),
+    ("RAlt", KeyCode::AltRight),
+];
+
+fn key_code(name: &str) -> Option<KeyCode> {
+    KEY_NAMES
+        .iter()
+        .find(|(key_name, _)| *key_name == name)
+        .map(|&(_, key)| key)
+}
+
+// the name of a key in the bindings file, the other way round from key_code
+fn key_name(key: KeyCode) -> Option<&'static str> {
+    KEY_NAMES
+        .iter()
+        .find(|&&(_, known_key)| known_key == key)
+        .map(|&(name, _)| name)
+}
+
+fn mouse_button(name: &str) -> Option<MouseButton> {
+    match name {
+        "Left" => Some(MouseButton::Left),
+        "Right" => Some(MouseButton::Right),
+        "Middle" => Some(MouseButton::Middle),
+        _ => None,
+    }
+}
+
+// South, East, North and West are the four buttons on the right of the gamepad (A, B, Y and X on an Xbox gamepad)
+fn gamepad_button(name: &str) -> Option<GamepadButtonType> {
+    let button = match name {
+        "South" => GamepadButtonType::South,
+        "East" => GamepadButtonType::East,
+        "North" => GamepadButtonType::North,
+        "West" => GamepadButtonType::West,
+        "LeftTrigger" => GamepadButtonType::LeftTrigger,
+        "LeftTrigger2" => GamepadButtonType::LeftTrigger2,
+        "RightTrigger" => GamepadButtonType::RightTrigger,
+        "RightTrigger2" => GamepadButtonType::RightTrigger2,
+        "Select" => GamepadButtonType::Select,
+        "Start" => GamepadButtonType::Start,
+        "DPadUp" => GamepadButtonType::DPadUp,
+        "DPadDown" => GamepadButtonType::DPadDown,
+        "DPadLeft" => GamepadButtonType::DPadLeft,
+        "DPadRight" => GamepadButtonType::DPadRight,
+        _ => return None,
+    };
+    Some(button)
+}
+
+// turns this frame's keyboard, mouse and gamepad input into InputActions.
+// Runs in PreUpdate, right after Bevy has read the input, so every system later in the frame sees the same actions
+#[allow(clippy::too_many_arguments)]
+pub fn read_input_actions(
+    bindings: Res<InputBindings>,
+    mut actions: ResMut<InputActions>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    mut cursor_moved_events: EventReader<CursorMoved>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+) {
+    // true if any of the bound keys/buttons, on any connected gamepad, is held or was just pressed
+    let gamepad_held = |buttons: &[GamepadButtonType]| {
+        gamepads.iter().any(|gamepad| {
+            gamepad_buttons.any_pressed(
+                buttons
+                    .iter()
+                    .map(|&button| GamepadButton::new(gamepad, button)),
+            )
+        })
+    };
+    let gamepad_just_pressed = |buttons: &[GamepadButtonType]| {
+        gamepads.iter().any(|gamepad| {
+            gamepad_buttons.any_just_pressed(
+                buttons
+                    .iter()
+                    .map(|&button| GamepadButton::new(gamepad, button)),
+            )
+        })
+    };
+
+    let mut movement = 0.0;
+    if keys.any_pressed(bindings.move_left.iter().copied())
+        || gamepad_held(&bindings.gamepad_move_left)
+    {
+        movement -= 1.0;
+    }
+    if keys.any_pressed(bindings.move_right.iter().copied())
+        || gamepad_held(&bindings.gamepad_move_right)
+    {
+        movement += 1.0;
+    }
+    // a stick pushed halfway moves the paddle at half speed
+    for gamepad in gamepads.iter() {
+        let stick = gamepad_axes
+            .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX))
+            .unwrap_or(0.0);
+        if stick.abs() > bindings.stick_dead_zone {
+            movement += stick;
+        }
+    }
+    actions.movement = movement.clamp(-1.0, 1.0);
+
+    // the mouse only takes over once it moves, and keys or the stick take back over as soon as they are used
+    if actions.movement != 0.0 {
+        actions.paddle_target = None;
+    }
+    // only the last cursor position of the frame matters
+    if let Some(cursor) = cursor_moved_events.iter().last() {
+        if bindings.mouse_paddle && window_query.get(cursor.window).is_ok() {
+            if let Ok((camera, camera_transform)) = camera_query.get_single() {
+                // from window pixels to the position in the game world, where the paddle is
+                actions.paddle_target = camera
+                    .viewport_to_world_2d(camera_transform, cursor.position)
+                    .map(|position| position.x);
+            }
+        }
+    }
+
+    actions.launch_pressed = keys.any_just_pressed(bindings.launch.iter().copied())
+        || mouse_buttons.any_just_pressed(bindings.mouse_launch.iter().copied())
+        || gamepad_just_pressed(&bindings.gamepad_launch);
+    actions.launch_held = keys.any_pressed(bindings.launch.iter().copied())
+        || mouse_buttons.any_pressed(bindings.mouse_launch.iter().copied())
+        || gamepad_held(&bindings.gamepad_launch);
+    actions.pause_pressed = keys.any_just_pressed(bindings.pause.iter().copied())
+        || gamepad_just_pressed(&bindings.gamepad_pause);
+
+    actions.menu_up = keys.any_just_pressed(bindings.menu_up.iter().copied())
+        || gamepad_just_pressed(&bindings.gamepad_menu_up);
+    actions.menu_down = keys.any_just_pressed(bindings.menu_down.iter().copied())
+        || gamepad_just_pressed(&bindings.gamepad_menu_down);
+    actions.menu_left = keys.any_just_pressed(bindings.move_left.iter().copied())
+        || gamepad_just_pressed(&bindings.gamepad_move_left);
+    actions.menu_right = keys.any_just_pressed(bindings.move_right.iter().copied())
+        || gamepad_just_pressed(&bindings.gamepad_move_right);
+    actions.menu_confirm = keys.any_just_pressed(bindings.menu_confirm.iter().copied())
+        || gamepad_just_pressed(&bindings.gamepad_menu_confirm);
+    actions.menu_back = keys.any_just_pressed(bindings.menu_back.iter().copied())
+        || gamepad_just_pressed(&bindings.gamepad_menu_back);
+    actions.continue_run = keys.any_just_pressed(bindings.continue_run.iter().copied())
+        || gamepad_just_pressed(&bindings.gamepad_continue_run);
+    actions.main_menu = keys.any_just_pressed(bindings.main_menu.iter().copied())
+        || gamepad_just_pressed(&bindings.gamepad_main_menu);
+    actions.rebuild_level = keys.any_just_pressed(bindings.rebuild_level.iter().copied())
+        || gamepad_just_pressed(&bindings.gamepad_rebuild_level);
+    actions.toggle_mute = keys.any_just_pressed(bindings.toggle_mute.iter().copied())
+        || gamepad_just_pressed(&bindings.gamepad_toggle_mute);
+    actions.master_volume_down = keys.any_just_pressed(bindings.master_volume_down.iter().copied())
+        || gamepad_just_pressed(&bindings.gamepad_master_volume_down);
+    actions.master_volume_up = keys.any_just_pressed(bindings.master_volume_up.iter().copied())
+        || gamepad_just_pressed(&bindings.gamepad_master_volume_up);
+    actions.sfx_volume_down = keys.any_just_pressed(bindings.sfx_volume_down.iter().copied())
+        || gamepad_just_pressed(&bindings.gamepad_sfx_volume_down);
+    actions.sfx_volume_up = keys.any_just_pressed(bindings.sfx_volume_up.iter().copied())
+        || gamepad_just_pressed(&bindings.gamepad_sfx_volume_up);
+}
@@ -0,0 +1,91 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use directories::ProjectDirs;
+use serde::{de::DeserializeOwned, Serialize};
+
+// ** Data files **
+// The high scores and the saved run are RON files kept in the data folder the system gives to the game
+// (e.g. ~/.local/share/breakout on Linux, %APPDATA%\breakout\data on Windows).
+// Every file carries the version of its format, so a file written by another version of the game is recognised
+// instead of being misread. Files are written next to the real file first and then renamed over it, so a crash
+// while writing can't leave a half written file behind.
+
+// the contents of a data file. VERSION must be bumped whenever the contents change
+pub trait VersionedFile: Serialize + DeserializeOwned {
+    // the version this game writes and reads
+    const VERSION: u32;
+
+    // the version the file was written with
+    fn version(&self) -> u32;
+}
+
+// everything that can go wrong with a data file
+#[derive(Debug)]
+pub enum DataFileError {
+    Read(std::io::Error),
+    Parse(ron::error::SpannedError),
+    UnknownVersion { found: u32, expected: u32 },
+    Write(std::io::Error),
+    Serialize(ron::Error),
+}
+
+impl fmt::Display for DataFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DataFileError::Read(err) => write!(f, "could not read the file: {err}"),
+            DataFileError::Parse(err) => write!(f, "could not understand the file: {err}"),
+            DataFileError::UnknownVersion { found, expected } => write!(
+                f,
+                "the file is version {found}, this game reads version {expected}"
+            ),
+            DataFileError::Write(err) => write!(f, "could not write the file: {err}"),
+            DataFileError::Serialize(err) => write!(f, "could not turn the data into text: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for DataFileError {}
+
+impl From<ron::error::SpannedError> for DataFileError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        DataFileError::Parse(err)
+    }
+}
+
+impl From<ron::Error> for DataFileError {
+    fn from(err: ron::Error) -> Self {
+        DataFileError::Serialize(err)
+    }
+}
+
+// where a file of the game's data goes, None when the system doesn't have a data folder
+pub fn data_file_path(file_name: &str) -> Option<PathBuf> {
+    ProjectDirs::from("", "", "breakout").map(|dirs| dirs.data_dir().join(file_name))
+}
+
+// reads a data file and checks it was written in the version this game knows
+pub fn read_data_file<T: VersionedFile>(path: &Path) -> Result<T, DataFileError> {
+    let text = std::fs::read_to_string(path).map_err(DataFileError::Read)?;
+    let data: T = ron::from_str(&text)?;
+    if data.version() != T::VERSION {
+        return Err(DataFileError::UnknownVersion {
+            found: data.version(),
+            expected: T::VERSION,
+        });
+    }
+    Ok(data)
+}
+
+// writes a data file, creating the data folder when it doesn't exist yet
+pub fn write_data_file<T: VersionedFile>(path: &Path, data: &T) -> Result<(), DataFileError> {
+    let text = ron::ser::to_string_pretty(data, ron::ser::PrettyConfig::default())?;
+    if let Some(folder) = path.parent() {
+        std::fs::create_dir_all(folder).map_err(DataFileError::Write)?;
+    }
+    let temporary_path = path.with_extension("ron.tmp");
+    std::fs::write(&temporary_path, text).map_err(DataFileError::Write)?;
+    std::fs::rename(&temporary_path, path).map_err(DataFileError::Write)
+}
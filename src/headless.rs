@@ -0,0 +1,207 @@
+use bevy::{app::AppExit, log::LogPlugin, prelude::*, time::TimeUpdateStrategy};
+
+use crate::{
+    advance_level, check_level_cleared, controls::InputActions, despawn_screen,
+    highscore::HighScores, reset_run, rng::GameRng, serve::StuckToPaddle, spawn_playfield, Ball,
+    CurrentLevel, GameState, GameplayPlugin, OnGameScreen, OnNoLevelsScreen, Paddle, Scoreboard,
+    Velocity,
+};
+
+// ** Headless mode **
+// `cargo run -- --headless 1000` plays 1000 rounds with MinimalPlugins: no window, renderer, sound or keyboard, so it
+// runs on machines without a display (like CI). GameplayPlugin gives it the same paddle, ball and brick systems as
+// the game, and an autopilot plays: it keeps the paddle under the lowest falling ball and launches balls straight away.
+// Every update moves the game forward by exactly one FixedUpdate step, however fast or slow the machine is.
+// A round starts on the first level and ends when the last ball is lost (or after MAX_ROUND_SECONDS), then its score
+// is printed. Nothing is written to the high scores or the saved run.
+// The same seed (printed at the end, given with --seed) plays every round the same way again.
+
+// a round where the ball is never lost (the autopilot can be very good) is stopped after this many seconds of play
+const MAX_ROUND_SECONDS: f32 = 600.0;
+
+// the results of the rounds played so far
+#[derive(Resource)]
+pub struct SimulationReport {
+    rounds_wanted: usize,
+    scores: Vec<usize>,
+    // how many rounds were stopped by MAX_ROUND_SECONDS
+    timed_out: usize,
+    // FixedUpdate steps played in the round going on
+    round_steps: u32,
+    round_timed_out: bool,
+    // false until every round has been played, it stays false when there is no level to play
+    completed: bool,
+}
+
+// plays `rounds` rounds without a window and prints the scores. Returns false when they couldn't be played
+pub fn run_headless(rounds: usize, seed: Option<u64>) -> bool {
+    // the same step as the game uses (1/60 of a second)
+    let time_step = FixedTime::default().period;
+
+    let mut app = App::new();
+    app.add_plugins((
+        // the task pools, Time and FixedUpdate, without anything that needs a window
+        MinimalPlugins,
+        // the level files are loaded through the AssetServer, like in the game
+        AssetPlugin::default(),
+        LogPlugin {
+            // without a renderer there is no loader for the ball texture, and the AssetServer would warn about it
+            // for every ball spawned
+            filter: "wgpu=error,naga=warn,bevy_asset=error".into(),
+            ..default()
+        },
+    ))
+    // Time moves forward by exactly time_step every update instead of following the clock,
+    // so every update runs FixedUpdate once
+    .insert_resource(TimeUpdateStrategy::ManualDuration(time_step))
+    .insert_resource(FixedTime::new(time_step))
+    .add_plugins(GameplayPlugin { seed })
+    // an empty table, the real one isn't read or changed
+    .insert_resource(HighScores::default())
+    .insert_resource(SimulationReport {
+        rounds_wanted: rounds,
+        scores: Vec::new(),
+        timed_out: 0,
+        round_steps: 0,
+        round_timed_out: false,
+        completed: false,
+    })
+    .add_systems(PreUpdate, play_automatically)
+    .add_systems(
+        Update,
+        stop_without_levels.run_if(in_state(GameState::Loading)),
+    )
+    .add_systems(
+        FixedUpdate,
+        // after the game's own checks, which can also end the round
+        limit_round_time
+            .after(check_level_cleared)
+            .run_if(in_state(GameState::Playing)),
+    )
+    // the main menu and the level cleared screen are skipped straight away
+    .add_systems(OnEnter(GameState::MainMenu), start_playing)
+    .add_systems(OnExit(GameState::MainMenu), reset_run)
+    .add_systems(
+        OnEnter(GameState::Playing),
+        spawn_playfield.run_if(not(any_with_component::<Paddle>())),
+    )
+    .add_systems(OnEnter(GameState::LevelCleared), start_playing)
+    .add_systems(
+        OnExit(GameState::LevelCleared),
+        (despawn_screen::<OnGameScreen>, advance_level),
+    )
+    .add_systems(OnEnter(GameState::NewHighScore), finish_round)
+    .add_systems(
+        OnExit(GameState::NewHighScore),
+        despawn_screen::<OnGameScreen>,
+    )
+    .add_systems(OnEnter(GameState::GameOver), finish_round)
+    .add_systems(OnExit(GameState::GameOver), despawn_screen::<OnGameScreen>);
+
+    // updated here instead of with app.run(), which would keep the App (and the report in it) to itself.
+    // finish() and cleanup() are what the runner started by run() does before the first update
+    app.finish();
+    app.cleanup();
+    while app.world.resource::<Events<AppExit>>().is_empty() {
+        app.update();
+    }
+    app.world.resource::<SimulationReport>().completed
+}
+
+// the autopilot: heads for the lowest ball that is coming down and launches the balls sitting on the paddle
+fn play_automatically(
+    mut actions: ResMut<InputActions>,
+    ball_query: Query<(&Transform, &Velocity, Option<&StuckToPaddle>), With<Ball>>,
+) {
+    let lowest_falling_ball = ball_query
+        .iter()
+        .filter(|(_, velocity, _)| velocity.y < 0.0)
+        .min_by(|(a, _, _), (b, _, _)| a.translation.y.total_cmp(&b.translation.y));
+
+    *actions = InputActions {
+        // the paddle goes there like it follows the mouse, at up to its full speed
+        paddle_target: lowest_falling_ball.map(|(transform, _, _)| transform.translation.x),
+        launch_pressed: ball_query.iter().any(|(_, _, stuck)| stuck.is_some()),
+        // keeps the laser firing when the paddle has it
+        launch_held: true,
+        ..default()
+    };
+}
+
+// there is nothing to play without a level, check_levels_loaded has already logged why
+fn stop_without_levels(
+    no_levels_screen: Query<(), With<OnNoLevelsScreen>>,
+    mut app_exit_events: EventWriter<AppExit>,
+) {
+    if !no_levels_screen.is_empty() {
+        app_exit_events.send(AppExit);
+    }
+}
+
+fn start_playing(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::Playing);
+}
+
+// counts the steps of the round, and ends it once it has gone on for MAX_ROUND_SECONDS
+fn limit_round_time(
+    time_step: Res<FixedTime>,
+    mut report: ResMut<SimulationReport>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    report.round_steps += 1;
+    let seconds = report.round_steps as f32 * time_step.period.as_secs_f32();
+    if seconds >= MAX_ROUND_SECONDS && !report.round_timed_out {
+        report.round_timed_out = true;
+        next_state.set(GameState::GameOver);
+    }
+}
+
+// prints the result of the round that just ended, then starts the next one, or stops the app after the last one
+fn finish_round(
+    score: Res<Scoreboard>,
+    current_level: Res<CurrentLevel>,
+    time_step: Res<FixedTime>,
+    rng: Res<GameRng>,
+    mut report: ResMut<SimulationReport>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut app_exit_events: EventWriter<AppExit>,
+) {
+    let seconds = report.round_steps as f32 * time_step.period.as_secs_f32();
+    println!(
+        "round {}: score {}, reached level {}, {seconds:.1} seconds{}",
+        report.scores.len() + 1,
+        score.score,
+        current_level.index + 1,
+        if report.round_timed_out {
+            " (stopped, it went on too long)"
+        } else {
+            ""
+        },
+    );
+    report.scores.push(score.score);
+    if report.round_timed_out {
+        report.timed_out += 1;
+    }
+    report.round_steps = 0;
+    report.round_timed_out = false;
+
+    if report.scores.len() < report.rounds_wanted {
+        // the main menu is skipped, this goes straight on to a new run from the first level
+        next_state.set(GameState::MainMenu);
+        return;
+    }
+
+    let total: usize = report.scores.iter().sum();
+    println!(
+        "{} rounds: average score {:.1}, best {}, worst {}, {} stopped after {MAX_ROUND_SECONDS} seconds",
+        report.scores.len(),
+        total as f32 / report.scores.len() as f32,
+        report.scores.iter().max().unwrap_or(&0),
+        report.scores.iter().min().unwrap_or(&0),
+        report.timed_out,
+    );
+    let seed = rng.seed();
+    println!("seed {seed}, run again with --seed {seed} to play the same rounds");
+    report.completed = true;
+    app_exit_events.send(AppExit);
+}
@@ -0,0 +1,281 @@
+use std::{
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{prelude::*, window::ReceivedCharacter};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    controls::{Binding, InputActions, InputBindings},
+    data_file::{data_file_path, read_data_file, write_data_file, DataFileError, VersionedFile},
+    spawn_menu_text, CurrentLevel, GameState, Scoreboard, MENU_TITLE_FONT_SIZE, SCORE_COLOR,
+};
+
+// ** High scores **
+// The best MAX_HIGH_SCORES runs are kept in high_scores.ron, in the data folder of the game (see data_file.rs).
+// A run that ends with a score good enough for the table goes to the NewHighScore screen, where the player types a name.
+// A missing file just means an empty table. A file that can't be read is put aside as high_scores.ron.bak
+// (so it isn't lost) and the table starts over.
+
+const MAX_HIGH_SCORES: usize = 10;
+const MAX_NAME_LENGTH: usize = 12;
+// the name used when the player confirms without typing anything
+const DEFAULT_NAME: &str = "PLAYER";
+const HIGH_SCORES_FILE_NAME: &str = "high_scores.ron";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HighScoreEntry {
+    name: String,
+    score: usize,
+    // the level the run ended on, counting from 1
+    level: usize,
+    // when the run ended, in seconds since 1970-01-01 (UTC)
+    date: u64,
+}
+
+// what is written to the file: the entries, and the version of the format they are written in
+#[derive(Serialize, Deserialize)]
+struct HighScoresFile {
+    version: u32,
+    entries: Vec<HighScoreEntry>,
+}
+
+impl VersionedFile for HighScoresFile {
+    // bump this when HighScoreEntry changes
+    const VERSION: u32 = 1;
+
+    fn version(&self) -> u32 {
+        self.version
+    }
+}
+
+// the table, best score first
+#[derive(Resource, Default)]
+pub struct HighScores {
+    entries: Vec<HighScoreEntry>,
+    // where the table is saved, None when the system has no data folder for the game (it is then only kept until the game closes)
+    path: Option<PathBuf>,
+}
+
+impl HighScores {
+    // true when `score` would get a place in the table
+    pub fn qualifies(&self, score: usize) -> bool {
+        score > 0
+            && (self.entries.len() < MAX_HIGH_SCORES
+                || self
+                    .entries
+                    .last()
+                    .is_none_or(|lowest| score > lowest.score))
+    }
+
+    // puts the entry in its place, and drops the lowest one if the table is full.
+    // An entry with the same score as an older one goes below it
+    fn insert(&mut self, entry: HighScoreEntry) {
+        let index = self
+            .entries
+            .partition_point(|other| other.score >= entry.score);
+        self.entries.insert(index, entry);
+        self.entries.truncate(MAX_HIGH_SCORES);
+    }
+
+    // the table as text, one run per line
+    pub fn table_text(&self) -> String {
+        if self.entries.is_empty() {
+            return "No high scores yet".to_string();
+        }
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                format!(
+                    "{}. {:<width$} {:>6}  level {}  {}\n",
+                    index + 1,
+                    entry.name,
+                    entry.score,
+                    entry.level,
+                    format_date(entry.date),
+                    width = MAX_NAME_LENGTH
+                )
+            })
+            .collect()
+    }
+
+    fn save(&self) -> Result<(), DataFileError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let file = HighScoresFile {
+            version: HighScoresFile::VERSION,
+            entries: self.entries.clone(),
+        };
+        write_data_file(path, &file)
+    }
+}
+
+// reads the table when the game starts. The game can always be played, whatever state the file is in
+pub fn load_high_scores(mut commands: Commands) {
+    let Some(path) = data_file_path(HIGH_SCORES_FILE_NAME) else {
+        warn!("no data folder found, the high scores won't be saved");
+        commands.insert_resource(HighScores::default());
+        return;
+    };
+
+    let entries = match read_data_file::<HighScoresFile>(&path) {
+        Ok(file) => file.entries,
+        Err(DataFileError::Read(err)) if err.kind() == std::io::ErrorKind::NotFound => {
+            info!(
+                "no high scores yet, they will be saved in {}",
+                path.display()
+            );
+            Vec::new()
+        }
+        Err(err) => {
+            // the broken file is kept aside, in case the player wants to look at it or fix it
+            let backup_path = path.with_extension("ron.bak");
+            warn!(
+                "invalid high scores file {}: {err}, starting a new table (the old file is moved to {})",
+                path.display(),
+                backup_path.display()
+            );
+            if let Err(err) = std::fs::rename(&path, &backup_path) {
+                warn!("could not move the old high scores file: {err}");
+            }
+            Vec::new()
+        }
+    };
+
+    let mut high_scores = HighScores {
+        entries: Vec::new(),
+        path: Some(path),
+    };
+    // inserted one by one, so a file edited by hand still ends up sorted and no longer than the table
+    for entry in entries {
+        high_scores.insert(entry);
+    }
+    commands.insert_resource(high_scores);
+}
+
+// the name being typed on the NewHighScore screen
+#[derive(Resource, Default)]
+pub struct NameEntry {
+    name: String,
+}
+
+#[derive(Component)]
+pub struct OnNewHighScoreScreen;
+
+// marks the text of the NewHighScore screen, update_name_entry_text rewrites the name in it as the player types
+#[derive(Component)]
+pub(crate) struct NameEntryText;
+
+pub fn show_name_entry(
+    mut commands: Commands,
+    mut name_entry: ResMut<NameEntry>,
+    score: Res<Scoreboard>,
+    bindings: Res<InputBindings>,
+) {
+    name_entry.name.clear();
+    let text = spawn_menu_text(
+        &mut commands,
+        OnNewHighScoreScreen,
+        &format!("NEW HIGH SCORE\n{}", score.score),
+        &format!(
+            "Type your name and press {}\n",
+            bindings.names(Binding::MenuConfirm)
+        ),
+    );
+    commands.entity(text).insert(NameEntryText);
+}
+
+// typing adds letters to the name, backspace removes the last one, and the confirm action (Enter)
+// puts the run in the table, saves it and moves on to the game over screen
+#[allow(clippy::too_many_arguments)]
+pub fn enter_high_score_name(
+    mut received_characters: EventReader<ReceivedCharacter>,
+    keyboard_input: Res<Input<KeyCode>>,
+    actions: Res<InputActions>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut name_entry: ResMut<NameEntry>,
+    mut high_scores: ResMut<HighScores>,
+    score: Res<Scoreboard>,
+    current_level: Res<CurrentLevel>,
+) {
+    // this runs in every state so that keys pressed while playing (A, D, ...) are thrown away
+    // instead of showing up in the name
+    if *state.get() != GameState::NewHighScore {
+        received_characters.clear();
+        return;
+    }
+
+    for received in received_characters.iter() {
+        // is_control() skips the characters sent for Enter, backspace, tab, ...
+        if !received.char.is_control() && name_entry.name.chars().count() < MAX_NAME_LENGTH {
+            name_entry.name.push(received.char);
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        name_entry.name.pop();
+    }
+
+    if !actions.menu_confirm {
+        return;
+    }
+    let name = name_entry.name.trim();
+    high_scores.insert(HighScoreEntry {
+        name: if name.is_empty() { DEFAULT_NAME } else { name }.to_string(),
+        score: score.score,
+        level: current_level.index + 1,
+        date: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since_epoch| since_epoch.as_secs()),
+    });
+    if let Err(err) = high_scores.save() {
+        warn!("could not save the high scores: {err}");
+    }
+    next_state.set(GameState::GameOver);
+}
+
+pub fn update_name_entry_text(
+    name_entry: Res<NameEntry>,
+    mut query: Query<&mut Text, With<NameEntryText>>,
+) {
+    if !name_entry.is_changed() {
+        return;
+    }
+    for mut text in &mut query {
+        // the name goes under the title and hint from spawn_menu_text, the underscore shows where the next letter goes
+        text.sections.truncate(2);
+        text.sections.push(TextSection::new(
+            format!("{}_", name_entry.name),
+            TextStyle {
+                font_size: MENU_TITLE_FONT_SIZE,
+                color: SCORE_COLOR,
+                ..default()
+            },
+        ));
+    }
+}
+
+// turns seconds since 1970-01-01 into a year-month-day date (UTC)
+fn format_date(seconds: u64) -> String {
+    // the days since 1970-01-01 are counted in eras of 400 years, which always have the same number of days.
+    // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    // months are counted from March, so the leap day is at the end of the year
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{year:04}-{month:02}-{day:02}")
+}
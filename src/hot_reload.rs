@@ -0,0 +1,164 @@
+use std::time::SystemTime;
+
+use bevy::prelude::*;
+
+use crate::{
+    ball_speed::BallSpeed,
+    config::{read_config_file, GameConfig, GAME_CONFIG_PATH},
+    controls::{Binding, InputActions, InputBindings},
+    level::{Level, LevelLoaderConfig, Levels},
+    spawn_level_bricks, Brick, CurrentLevel, OnGameScreen, MENU_HINT_FONT_SIZE,
+    SCOREBOARD_TEXT_PADDING, SCORE_COLOR,
+};
+
+// ** Hot reload **
+// Edits to config/game.ron and to the level files are picked up while the game is running:
+// - config/game.ron is checked every CONFIG_CHECK_INTERVAL seconds. The systems read GameConfig every tick, so speeds,
+//   spin, the paddle size, ... change at once. The walls, the ball size and the brick layout are only used when things
+//   are spawned, so they change from the next round (or the next rebuild). The level files are loaded again as well,
+//   because their defaults (ball speed, brick color) and how many bricks fit come from the config.
+// - the level files are watched by the AssetServer (see watch_for_changes in main). When the level being played
+//   changes, its new ball speed is used at once and a message offers to rebuild the bricks with the rebuild_level
+//   binding (F5 by default, see controls.rs).
+// A file with a mistake is reported, and the game keeps what it had.
+
+const CONFIG_CHECK_INTERVAL: f32 = 0.5;
+
+// what reload_changed_config remembers about config/game.ron between two checks
+#[derive(Default)]
+pub struct ConfigFileState {
+    checked: bool,
+    // when the file was last changed, None when it doesn't exist
+    modified: Option<SystemTime>,
+    // seconds since the last check
+    since_check: f32,
+}
+
+// marks the message offering to rebuild the bricks of the level
+#[derive(Component)]
+pub struct RebuildLevelText;
+
+// reads config/game.ron again when it has been saved since the last check
+pub fn reload_changed_config(
+    time: Res<Time>,
+    // Local is a value kept between runs of this system
+    mut file: Local<ConfigFileState>,
+    mut config: ResMut<GameConfig>,
+    loader_config: Res<LevelLoaderConfig>,
+    levels: Res<Levels>,
+    asset_server: Res<AssetServer>,
+) {
+    file.since_check += time.delta_seconds();
+    if file.checked && file.since_check < CONFIG_CHECK_INTERVAL {
+        return;
+    }
+    file.since_check = 0.0;
+
+    // metadata() fails when the file doesn't exist, modified() on systems that don't keep the date
+    let modified = std::fs::metadata(GAME_CONFIG_PATH)
+        .and_then(|metadata| metadata.modified())
+        .ok();
+    // the first check only remembers the date, the config was just read when the game started
+    let changed = file.checked && modified.is_some() && modified != file.modified;
+    file.checked = true;
+    file.modified = modified;
+    if !changed {
+        return;
+    }
+
+    match read_config_file() {
+        Ok(new_config) => {
+            info!("{GAME_CONFIG_PATH} changed, using the new config");
+            loader_config.set(new_config.clone());
+            *config = new_config;
+            // the levels take their defaults and how many bricks fit from the config
+            for handle in &levels.handles {
+                if let Some(path) = asset_server.get_handle_path(handle) {
+                    asset_server.reload_asset(path);
+                }
+            }
+        }
+        Err(err) => {
+            warn!("invalid config file {GAME_CONFIG_PATH}: {err}, keeping the current config")
+        }
+    }
+}
+
+// when the file of the level being played changes, uses its ball speed and offers to rebuild its bricks
+#[allow(clippy::too_many_arguments)]
+pub fn react_to_level_changes(
+    mut commands: Commands,
+    mut level_events: EventReader<AssetEvent<Level>>,
+    levels: Res<Levels>,
+    level_assets: Res<Assets<Level>>,
+    current_level: Res<CurrentLevel>,
+    config: Res<GameConfig>,
+    bindings: Res<InputBindings>,
+    mut ball_speed: ResMut<BallSpeed>,
+    rebuild_text_query: Query<(), With<RebuildLevelText>>,
+) {
+    let current_handle = levels.handle(current_level.index);
+    // any() stops at the first match, clear() marks the events after it as read too
+    let changed = level_events.iter().any(
+        |event| matches!(event, AssetEvent::Modified { handle } if Some(handle) == current_handle),
+    );
+    level_events.clear();
+    if !changed {
+        return;
+    }
+    let Some(level) = levels.get(current_level.index, &level_assets) else {
+        return;
+    };
+
+    info!("the level file of {} changed", level.name);
+    ball_speed.rebase(level.ball_speed, config.ball_max_speed);
+    if rebuild_text_query.is_empty() {
+        commands.spawn((
+            TextBundle::from_section(
+                format!(
+                    "The level file changed, press {} to rebuild the bricks",
+                    bindings.names(Binding::RebuildLevel)
+                ),
+                TextStyle {
+                    font_size: MENU_HINT_FONT_SIZE,
+                    color: SCORE_COLOR,
+                    ..default()
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                bottom: SCOREBOARD_TEXT_PADDING,
+                left: SCOREBOARD_TEXT_PADDING,
+                ..default()
+            }),
+            RebuildLevelText,
+            OnGameScreen,
+        ));
+    }
+}
+
+// the rebuild_level action puts back every brick of the level as the file now describes it, at full health.
+// The balls, paddle, score and lives are kept
+#[allow(clippy::too_many_arguments)]
+pub fn rebuild_level_on_request(
+    mut commands: Commands,
+    actions: Res<InputActions>,
+    config: Res<GameConfig>,
+    levels: Res<Levels>,
+    level_assets: Res<Assets<Level>>,
+    current_level: Res<CurrentLevel>,
+    brick_query: Query<Entity, With<Brick>>,
+    rebuild_text_query: Query<Entity, With<RebuildLevelText>>,
+) {
+    if rebuild_text_query.is_empty() || !actions.rebuild_level {
+        return;
+    }
+    let Some(level) = levels.get(current_level.index, &level_assets) else {
+        return;
+    };
+
+    for entity in brick_query.iter().chain(rebuild_text_query.iter()) {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_level_bricks(&mut commands, &config, level);
+}
@@ -0,0 +1,400 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::{TypePath, TypeUuid},
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{GameConfig, GAME_CONFIG_PATH};
+
+// ** Level files **
+// Levels live in assets/levels/ as "<name>.level.ron" files and are played in file name order.
+// A level file looks like this:
+//
+// (
+//     name: "Level 1",
+//     ball_speed: 400.0,
+//     legend: {
+//         'a': (health: 1, color: (0.5, 0.5, 1.0)),
+//         'b': (health: 3, color: (0.3, 0.3, 0.8), damaged_color: (0.7, 0.7, 1.0), hit_score: 1, destroy_score: 5),
+//         's': (kind: Steel, color: (0.6, 0.6, 0.6)),
+//         'x': (kind: Explosive(radius: 100.0), color: (1.0, 0.4, 0.1)),
+//     },
+//     grid: [
+//         "aaaaaaaa",
+//         "a.bbbb.a",
+//         "s..xx..s",
+//     ],
+// )
+//
+// Every line of the grid is a row of bricks, starting at the ceiling. Every character is a column,
+// starting at the left wall. '.' or ' ' leaves the spot empty, any other character must be in the legend.
+// How many rows and columns fit depends on the arena and brick sizes in config/game.ron.
+// ball_speed, health and color can be left out, they then default to ball.speed from config/game.ron, 1
+// and bricks.color from config/game.ron.
+// A brick with more than 1 health changes from `color` to `damaged_color` as it is hit (damaged_color defaults
+// to a lighter `color`). Every hit scores `hit_score` points (default 1) and breaking it `destroy_score` more (default 0).
+// `kind` makes a brick special, see BrickKind below. It defaults to Normal.
+
+// A level, as it is used by the game once the file has been loaded and checked
+// TypeUuid and TypePath are needed by Bevy to store the level in Assets<Level>
+#[derive(TypeUuid, TypePath)]
+#[uuid = "db20e534-ba81-4016-8a20-4c745761598c"]
+pub struct Level {
+    pub name: String,
+    // how fast the ball moves in this level, in units per second
+    pub ball_speed: f32,
+    pub bricks: Vec<LevelBrick>,
+}
+
+// one brick of a level
+pub struct LevelBrick {
+    // 0 is the row right under the ceiling
+    pub row: usize,
+    // 0 is the column next to the left wall
+    pub column: usize,
+    pub health: i8,
+    // the color at full health
+    pub color: Color,
+    // the color once only 1 health is left
+    pub damaged_color: Color,
+    pub hit_score: usize,
+    pub destroy_score: usize,
+    pub kind: BrickKind,
+}
+
+// what happens when a brick is hit (also written in saved runs, see savegame.rs)
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum BrickKind {
+    // loses 1 health per hit and is removed when none is left
+    #[default]
+    Normal,
+    // can't be broken, and doesn't have to be broken to clear the level
+    Steel,
+    // when it breaks, every other brick within `radius` (from center to center) is hit too
+    Explosive { radius: f32 },
+    // when it breaks, a new ball is launched from where it was
+    ExtraBall,
+    // when it breaks, it comes back with full health `delay` seconds later
+    Regenerating { delay: f32 },
+}
+
+// a .level.ron file as serde reads it, parse_level checks it and turns its rows into bricks
+#[derive(Deserialize)]
+struct LevelFile {
+    name: String,
+    // None when it is left out of the file, the ball speed of the config is used then
+    #[serde(default)]
+    ball_speed: Option<f32>,
+    legend: HashMap<char, BrickDescription>,
+    grid: Vec<String>,
+}
+
+// what a character of the grid stands for
+#[derive(Deserialize)]
+struct BrickDescription {
+    #[serde(default = "default_health")]
+    health: i8,
+    // red, green and blue between 0.0 and 1.0. None when it is left out of the file, the brick color of the config is used then
+    #[serde(default)]
+    color: Option<(f32, f32, f32)>,
+    // Option: when it is left out of the file, it is None and a lighter `color` is used
+    #[serde(default)]
+    damaged_color: Option<(f32, f32, f32)>,
+    #[serde(default = "default_hit_score")]
+    hit_score: usize,
+    #[serde(default)]
+    destroy_score: usize,
+    #[serde(default)]
+    kind: BrickKind,
+}
+
+fn default_health() -> i8 {
+    1
+}
+
+fn default_hit_score() -> usize {
+    1
+}
+
+// how much of white is mixed into `color` to get the default damaged_color
+const DEFAULT_DAMAGED_WHITENESS: f32 = 0.6;
+
+// why a level file is left out of the game
+#[derive(Debug)]
+enum LevelError {
+    // the file isn't valid RON or doesn't have the expected fields
+    Parse(ron::error::SpannedError),
+    InvalidBallSpeed(f32),
+    BallSpeedAboveMax { speed: f32, max: f32 },
+    NoBricks,
+    TooManyRows { rows: usize, max: usize },
+    TooManyColumns { row: usize, columns: usize, max: usize },
+    InvalidHealth { symbol: char, health: i8 },
+    InvalidExplosionRadius { symbol: char, radius: f32 },
+    InvalidRegenerationDelay { symbol: char, delay: f32 },
+    OnlySteelBricks,
+    UnknownSymbol { symbol: char, row: usize, column: usize },
+}
+
+// rows and columns are shown counted from 1, the way a designer reads the file
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LevelError::Parse(err) => write!(f, "could not read the level: {err}"),
+            LevelError::InvalidBallSpeed(speed) => {
+                write!(f, "ball_speed must be greater than 0, found {speed}")
+            }
+            LevelError::BallSpeedAboveMax { speed, max } => write!(
+                f,
+                "ball_speed is {speed}, more than speed_up.max_speed ({max}) in {GAME_CONFIG_PATH}"
+            ),
+            LevelError::NoBricks => write!(f, "the grid doesn't contain any brick"),
+            LevelError::TooManyRows { rows, max } => {
+                write!(f, "the grid has {rows} rows but only {max} fit in the arena")
+            }
+            LevelError::TooManyColumns { row, columns, max } => write!(
+                f,
+                "row {} has {columns} columns but only {max} fit in the arena",
+                row + 1
+            ),
+            LevelError::InvalidHealth { symbol, health } => write!(
+                f,
+                "brick '{symbol}' has health {health}, it must be at least 1"
+            ),
+            LevelError::InvalidExplosionRadius { symbol, radius } => write!(
+                f,
+                "brick '{symbol}' has explosion radius {radius}, it must be greater than 0"
+            ),
+            LevelError::InvalidRegenerationDelay { symbol, delay } => write!(
+                f,
+                "brick '{symbol}' has regeneration delay {delay}, it must be greater than 0"
+            ),
+            LevelError::OnlySteelBricks => write!(
+                f,
+                "every brick of the grid is steel, the level could never be cleared"
+            ),
+            LevelError::UnknownSymbol {
+                symbol,
+                row,
+                column,
+            } => write!(
+                f,
+                "'{symbol}' at row {}, column {} is not in the legend",
+                row + 1,
+                column + 1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+impl From<ron::error::SpannedError> for LevelError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        LevelError::Parse(err)
+    }
+}
+
+// turns the bytes of a level file into a Level, or explains what is wrong with it
+fn parse_level(bytes: &[u8], config: &GameConfig) -> Result<Level, LevelError> {
+    // the ? operator returns the error early, converted to a LevelError by the From impl above
+    // IMPLICIT_SOME lets the optional values be written as `color: (0.5, 0.5, 1.0)` instead of `color: Some(...)`
+    let file: LevelFile = ron::Options::default()
+        .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+        .from_bytes(bytes)?;
+
+    let ball_speed = file.ball_speed.unwrap_or(config.ball_speed);
+    if ball_speed <= 0.0 {
+        return Err(LevelError::InvalidBallSpeed(ball_speed));
+    }
+    if ball_speed > config.ball_max_speed {
+        return Err(LevelError::BallSpeedAboveMax {
+            speed: ball_speed,
+            max: config.ball_max_speed,
+        });
+    }
+    for (&symbol, description) in &file.legend {
+        if description.health < 1 {
+            return Err(LevelError::InvalidHealth {
+                symbol,
+                health: description.health,
+            });
+        }
+        match description.kind {
+            BrickKind::Explosive { radius } if radius <= 0.0 => {
+                return Err(LevelError::InvalidExplosionRadius { symbol, radius });
+            }
+            BrickKind::Regenerating { delay } if delay <= 0.0 => {
+                return Err(LevelError::InvalidRegenerationDelay { symbol, delay });
+            }
+            _ => {}
+        }
+    }
+
+    let (max_rows, max_columns) = config.brick_grid_size();
+    if file.grid.len() > max_rows {
+        return Err(LevelError::TooManyRows {
+            rows: file.grid.len(),
+            max: max_rows,
+        });
+    }
+
+    let mut bricks = Vec::new();
+    for (row, line) in file.grid.iter().enumerate() {
+        let columns = line.chars().count();
+        if columns > max_columns {
+            return Err(LevelError::TooManyColumns {
+                row,
+                columns,
+                max: max_columns,
+            });
+        }
+
+        for (column, symbol) in line.chars().enumerate() {
+            if symbol == '.' || symbol == ' ' {
+                continue;
+            }
+            let Some(description) = file.legend.get(&symbol) else {
+                return Err(LevelError::UnknownSymbol {
+                    symbol,
+                    row,
+                    column,
+                });
+            };
+            let color = match description.color {
+                Some((r, g, b)) => Color::rgb(r, g, b),
+                None => config.brick_color,
+            };
+            let damaged_color = match description.damaged_color {
+                Some((r, g, b)) => Color::rgb(r, g, b),
+                None => mix_colors(color, Color::WHITE, DEFAULT_DAMAGED_WHITENESS),
+            };
+            bricks.push(LevelBrick {
+                row,
+                column,
+                health: description.health,
+                color,
+                damaged_color,
+                hit_score: description.hit_score,
+                destroy_score: description.destroy_score,
+                kind: description.kind,
+            });
+        }
+    }
+
+    if bricks.is_empty() {
+        return Err(LevelError::NoBricks);
+    }
+    if bricks.iter().all(|brick| brick.kind == BrickKind::Steel) {
+        return Err(LevelError::OnlySteelBricks);
+    }
+
+    Ok(Level {
+        name: file.name,
+        ball_speed,
+        bricks,
+    })
+}
+
+// tells Bevy's AssetServer how to turn a .level.ron file into a Level.
+// It needs the GameConfig to know how many bricks fit and the defaults of the level files
+pub struct LevelLoader {
+    config: LevelLoaderConfig,
+}
+
+// the loader runs on other threads, outside of the ECS, so it can't read the GameConfig resource.
+// It shares this copy instead, which hot_reload.rs updates when config/game.ron changes.
+// Arc lets several owners share the value, RwLock lets one of them change it safely
+#[derive(Resource, Clone)]
+pub struct LevelLoaderConfig(Arc<RwLock<GameConfig>>);
+
+// a poisoned lock only means another thread panicked while holding it, the value inside is still usable
+impl LevelLoaderConfig {
+    fn get(&self) -> GameConfig {
+        self.0
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    pub fn set(&self, config: GameConfig) {
+        *self.0.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = config;
+    }
+}
+
+// FromWorld lets .init_asset_loader() build the loader from the resources of the app,
+// so the GameConfig resource must be inserted before it
+impl FromWorld for LevelLoader {
+    fn from_world(world: &mut World) -> Self {
+        let config = LevelLoaderConfig(Arc::new(RwLock::new(
+            world.resource::<GameConfig>().clone(),
+        )));
+        world.insert_resource(config.clone());
+        LevelLoader { config }
+    }
+}
+
+impl AssetLoader for LevelLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            // the AssetServer only logs the top level message, so the file name is put in it
+            let level = parse_level(bytes, &self.config.get()).map_err(|err| {
+                bevy::asset::Error::msg(format!(
+                    "invalid level file {}: {err}",
+                    load_context.path().display()
+                ))
+            })?;
+            load_context.set_default_asset(LoadedAsset::new(level));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+// the levels found in assets/levels/, in the order they are played
+#[derive(Resource, Default)]
+pub struct Levels {
+    pub handles: Vec<Handle<Level>>,
+}
+
+impl Levels {
+    // the level at the given index. An index past the last level (a saved run from a longer list of levels)
+    // gets the last level
+    pub fn get<'a>(&self, index: usize, assets: &'a Assets<Level>) -> Option<&'a Level> {
+        assets.get(self.handle(index)?)
+    }
+
+    // the handle of the level at the given index, with the last level repeated like in get()
+    pub fn handle(&self, index: usize) -> Option<&Handle<Level>> {
+        self.handles.get(index.min(self.handles.len().checked_sub(1)?))
+    }
+
+    // true for the last level, clearing it ends the run
+    pub fn is_last(&self, index: usize) -> bool {
+        index + 1 >= self.handles.len()
+    }
+}
+
+// mixes two colors: amount 0.0 gives `from`, 1.0 gives `to`, and anything in between a blend of both
+pub fn mix_colors(from: Color, to: Color, amount: f32) -> Color {
+    Color::rgb(
+        from.r() + (to.r() - from.r()) * amount,
+        from.g() + (to.g() - from.g()) * amount,
+        from.b() + (to.b() - from.b()) * amount,
+    )
+}
@@ -1,46 +1,59 @@
-use bevy::{math::*, prelude::*, sprite::collide_aabb::*};
+use std::time::Duration;
+
+use bevy::{
+    asset::{ChangeWatcher, LoadState},
+    math::*,
+    prelude::*,
+};
 use rand::prelude::*;
 
-//paddle
-// position the paddle 60 units above the bottom wall - is the y-coordinate
-// f32 is a 32-bit floating-point number
-const PADDLE_START_Y: f32 = BOTTOM_WALL + 60.;
-// Vec2 - representing 2D vector (120 units wide and 20 units tall)
-const PADDLE_SIZE: Vec2 = Vec2::new(120.0, 20.0);
-const PADDLE_COLOR: Color = Color::rgb(0.3, 0.3, 0.7);
-// speed of the paddle - representing the number of units the paddle moves per second per frame
-const PADDLE_SPEED: f32 = 500.0;
+mod audio;
+mod ball_speed;
+mod cli;
+mod collision;
+mod config;
+mod controls;
+mod data_file;
+mod headless;
+mod highscore;
+mod hot_reload;
+mod level;
+mod pause;
+mod powerup;
+mod rng;
+mod savegame;
+mod serve;
+
+use audio::{change_volume_on_input, load_collision_sounds, play_collision_sounds, AudioSettings};
+use ball_speed::{apply_ball_speed, speed_up_balls, BallSpeed};
+use cli::parse_command_line;
+use collision::{circle_penetration, sweep_circle, Contact};
+use config::{load_game_config, GameConfig};
+use controls::{load_input_bindings, read_input_actions, Binding, InputActions, InputBindings};
+use headless::run_headless;
+use highscore::{
+    enter_high_score_name, load_high_scores, show_name_entry, update_name_entry_text, HighScores,
+    NameEntry, OnNewHighScoreScreen,
+};
+use hot_reload::{react_to_level_changes, rebuild_level_on_request, reload_changed_config};
+use level::{mix_colors, BrickKind, Level, LevelLoader, Levels};
+use pause::{navigate_pause_menu, show_pause_menu, update_pause_menu_text, PauseMenu};
+use powerup::{
+    check_laser_hits, collect_power_ups, drop_power_ups, fire_lasers, resize_paddle,
+    stick_balls_to_paddle, tick_power_ups, ActivePowerUps,
+};
+use rng::GameRng;
+use savegame::{
+    continuing_saved_run, load_saved_run, restore_saved_run, save_run_on_exit, SavedRun,
+};
+use serve::{hold_stuck_balls, launch_stuck_balls, FreeBall, StuckToPaddle};
+
+// the sizes, speeds and colors of the paddle, ball, walls and bricks, and the number of lives,
+// are read from config/game.ron when the game starts, see config.rs and the GameConfig resource
 
 //ball
-const BALL_COLOR: Color = Color::rgb(1.0, 0.5, 0.5);
-const BALL_STARTING_POSITION: Vec3 = Vec3::new(0.0, -50.0, 1.0);
-const BALL_SIZE: Vec2 = Vec2::new(30.0, 30.0);
-const BALL_SPEED: f32 = 400.0;
-// ball move to the right and downward
-const BALL_INITIAL_DIRECTION: Vec2 = Vec2::new(0.5, -0.5);
-
-//wall
-const LEFT_WALL: f32 = -450.;
-const RIGHT_WALL: f32 = 450.;
-const BOTTOM_WALL: f32 = -300.;
-const TOP_WALL: f32 = 300.;
-
-// 10 units thick
-const WALL_THICKNESS: f32 = 10.0;
-// total width the game area enclosed by left and right walls
-const WALL_BLOCK_WIDTH: f32 = RIGHT_WALL - LEFT_WALL;
-const WALL_BLOCK_HEIGHT: f32 = TOP_WALL - BOTTOM_WALL;
-const WALL_COLOR: Color = Color::rgb(0.8, 0.8, 0.8);
-
-//bricks
-const BRICK_SIZE: Vec2 = Vec2::new(100., 30.);
-const BRICK_COLOR: Color = Color::rgb(0.5, 0.5, 1.0);
-const GAP_BETWEEN_PADDLE_AND_BRICKS: f32 = 270.0;
-const GAP_BETWEEN_BRICKS: f32 = 5.0;
-// vertical gap between the top row of the bricks and the ceiling(top boundary)
-const GAP_BETWEEN_BRICKS_AND_CEILING: f32 = 20.0;
-// horizontal gap between the bricks and the side boundaries (left and right walls)
-const GAP_BETWEEN_BRICKS_AND_SIDES: f32 = 20.0;
+// how many bounces the ball can make in a single FixedUpdate tick (e.g. into a corner between a wall and a brick)
+const MAX_BALL_CONTACTS_PER_TICK: usize = 4;
 
 //scoreboard
 const SCOREBOARD_FONT_SIZE: f32 = 40.0;
@@ -49,6 +62,11 @@ const SCOREBOARD_TEXT_PADDING: Val = Val::Px(5.0);
 const TEXT_COLOR: Color = Color::rgb(0.5, 0.5, 1.0);
 const SCORE_COLOR: Color = Color::rgb(1.0, 0.5, 0.5);
 
+//menus (main menu, pause, game over)
+const MENU_TITLE_FONT_SIZE: f32 = 60.0;
+const MENU_HINT_FONT_SIZE: f32 = 30.0;
+const MENU_COLOR: Color = Color::rgb(0.3, 0.3, 0.7);
+
 // ** Note **
 // use .insert_resource when you want to add globally accesible data that can be shared and modified by multiple systems
 // use .add_systems when u want to add logic that acts on entities and their components
@@ -58,40 +76,292 @@ const SCORE_COLOR: Color = Color::rgb(1.0, 0.5, 0.5);
 // FixedUpdate stage  is used for updates that need to happen at a consistent rate, independent of the frame rate e.g., 
 
 fn main() {
+    // `cargo run -- --headless 1000` plays 1000 rounds without a window and prints the results, see headless.rs
+    let command_line = parse_command_line();
+    if let Some(rounds) = command_line.headless_rounds {
+        if !run_headless(rounds, command_line.seed) {
+            std::process::exit(1);
+        }
+        return;
+    }
+
     App::new()
         // provide functionality like rendering, event handling, window management
-        .add_plugins(DefaultPlugins)
-        // add a resource to the application - a resource is a piece of data that can be accessed globally within the app
-        .insert_resource(ClearColor(Color::rgb(0.9, 0.9, 0.9)))
-        // adds a scoreboard resource to game with initial score 0 - the resource is globally accessible and can be used to track and display the player's score throughout the game
-        .insert_resource(Scoreboard { score: 0 })
+        .add_plugins(DefaultPlugins.set(AssetPlugin {
+            // the AssetServer loads the level files again when they are saved, see hot_reload.rs
+            watch_for_changes: ChangeWatcher::with_delay(Duration::from_millis(200)),
+            ..default()
+        }))
+        // the paddle, balls, bricks and levels, and the systems that play a round with them (see GameplayPlugin below)
+        .add_plugins(GameplayPlugin {
+            seed: command_line.seed,
+        })
+        // master/sound effects volume and mute, changed with the 0, -, =, [ and ] keys
+        .init_resource::<AudioSettings>()
+        .init_resource::<PauseMenu>()
+        // the name typed on the new high score screen. The table itself (HighScores) is read from disk by load_high_scores
+        .init_resource::<NameEntry>()
         // .add_systems is used to add systems. Systems are functions that run every frame and perform operations on entities and their components
         // run during the Update stage of the game loop
-        // closes the game window when the escape key is pressed
-        .add_systems(Update, (bevy::window::close_on_esc, update_scoreboard),)
+        .add_systems(
+            Update,
+            (
+                change_state_on_input,
+                // Escape opens the pause menu instead of closing the game, Quit in that menu closes it
+                (navigate_pause_menu, update_pause_menu_text)
+                    .chain()
+                    .run_if(in_state(GameState::Paused)),
+                update_scoreboard,
+                change_volume_on_input,
+                play_collision_sounds,
+                // quitting during a round keeps it on disk, to be continued from the main menu
+                save_run_on_exit
+                    .after(navigate_pause_menu)
+                    .run_if(in_state(GameState::Playing).or_else(in_state(GameState::Paused))),
+                // not limited to NewHighScore, it throws away what is typed in the other states itself
+                (enter_high_score_name, update_name_entry_text).chain(),
+                // edits to config/game.ron and the level files are used without restarting the game
+                reload_changed_config,
+                (react_to_level_changes, rebuild_level_on_request)
+                    .chain()
+                    .run_if(in_state(GameState::Playing).or_else(in_state(GameState::Paused))),
+            ),
+        )
+        // reads the keyboard, mouse and gamepads into InputActions, before any Update system looks at them
+        .add_systems(
+            PreUpdate,
+            read_input_actions.after(bevy::input::InputSystem),
+        )
         // runs once when the app starts
-        .add_systems(Startup, setup)
-        // used for physics updates and other operations that should occur at a fixed interval
         .add_systems(
-            FixedUpdate,
+            Startup,
+            (
+                load_collision_sounds,
+                load_input_bindings,
+                load_high_scores,
+                load_saved_run,
+            ),
+        )
+        // OnEnter systems run once when the game switches into a state, OnExit systems run once when it leaves it
+        .add_systems(
+            OnEnter(GameState::MainMenu),
+            // coming back to the menu from a paused game throws the unfinished round away
+            (despawn_screen::<OnGameScreen>, show_main_menu),
+        )
+        .add_systems(
+            OnExit(GameState::MainMenu),
+            (despawn_screen::<OnMainMenuScreen>, reset_run),
+        )
+        // only builds a new paddle, ball, walls and bricks when there isn't a round going on already,
+        // so that resuming from Paused carries on with the same round
+        // Continue in the main menu builds the level as usual, and restore_saved_run then puts the saved
+        // bricks, balls and paddle in place (apply_deferred spawns the level first, so it can be swapped)
+        .add_systems(
+            OnEnter(GameState::Playing),
+            (
+                spawn_playfield.run_if(not(any_with_component::<Paddle>())),
+                apply_deferred,
+                restore_saved_run.run_if(continuing_saved_run),
+            )
+                .chain(),
+        )
+        .add_systems(OnEnter(GameState::Paused), show_pause_menu)
+        .add_systems(OnExit(GameState::Paused), despawn_screen::<OnPauseScreen>)
+        .add_systems(OnEnter(GameState::LevelCleared), show_level_cleared)
+        // the cleared round is thrown away and OnEnter(GameState::Playing) builds the next level.
+        // Score and lives are carried forward
+        .add_systems(
+            OnExit(GameState::LevelCleared),
+            (
+                despawn_screen::<OnLevelClearedScreen>,
+                despawn_screen::<OnGameScreen>,
+                advance_level,
+            ),
+        )
+        .add_systems(OnEnter(GameState::NewHighScore), show_name_entry)
+        .add_systems(
+            OnExit(GameState::NewHighScore),
+            despawn_screen::<OnNewHighScoreScreen>,
+        )
+        .add_systems(OnEnter(GameState::GameOver), show_game_over)
+        // leaving the game over screen (restart or back to menu) clears the old round away
+        .add_systems(
+            OnExit(GameState::GameOver),
             (
-                move_paddle,
-                apply_velocity,
-                // ensures that collision checks happen after velocity have been applied
-                check_ball_collisions.after(apply_velocity),
+                despawn_screen::<OnGameOverScreen>,
+                despawn_screen::<OnGameScreen>,
+                reset_run,
             ),
         )
         // game start and continuously runs, executing teh registered systems each frame until the game is closed
         .run();
 }
 
+// Everything needed to play a round: the config, score, lives and levels, loading the levels, launching the ball and the
+// FixedUpdate systems (paddle, balls, bricks, power-ups). Menus, sound, input and drawing are left to the app using it:
+// the game in main() above, and the headless mode in headless.rs, which plays rounds without a window
+struct GameplayPlugin {
+    // the seed given with --seed, see rng.rs
+    seed: Option<u64>,
+}
+
+impl Plugin for GameplayPlugin {
+    fn build(&self, app: &mut App) {
+        // the sizes, speeds and colors from config/game.ron. The plugin is added after the ones that set up logging,
+        // so its warnings are logged. It is read before init_asset_loader::<LevelLoader>(), which needs it
+        let config = load_game_config();
+        // every random choice comes from here. --seed wins over the seed in the config file
+        let rng = GameRng::new(self.seed.or(config.seed));
+        let seed = rng.seed();
+        info!("random seed {seed}, start the game with --seed {seed} to play it again");
+
+        app
+            // add a resource to the application - a resource is a piece of data that can be accessed globally within the app
+            .insert_resource(config)
+            .insert_resource(rng)
+            // adds a scoreboard resource to game with initial score 0 - the resource is globally accessible and can be used to track and display the player's score throughout the game
+            .insert_resource(Scoreboard { score: 0 })
+            // the level being played, starts at the first one
+            .insert_resource(CurrentLevel {
+                index: 0,
+                starting_score: 0,
+            })
+            .init_resource::<Levels>()
+            // what the player is doing this frame (move, launch, pause), whether with keyboard, mouse or gamepad.
+            // In headless mode the autopilot fills it in instead
+            .init_resource::<InputActions>()
+            // the power-ups caught with the paddle and how long they have left
+            .init_resource::<ActivePowerUps>()
+            // how fast the balls move, it goes up during a life, see ball_speed.rs
+            .init_resource::<BallSpeed>()
+            // check_ball_collisions sends a CollisionEvent for every bounce. Other systems (damage_bricks, play_collision_sounds, ...)
+            // read them, so the physics doesn't need to know about everything that reacts to a hit
+            .add_event::<CollisionEvent>()
+            // damage_bricks sends a BrickHitEvent for every brick that lost health
+            .add_event::<BrickHitEvent>()
+            // teaches the AssetServer how to load the .level.ron files in assets/levels/
+            .add_asset::<Level>()
+            .init_asset_loader::<LevelLoader>()
+            // registers GameState so systems can check which state the game is in (and react when it changes)
+            .add_state::<GameState>()
+            .add_systems(
+                Update,
+                (
+                    check_levels_loaded.run_if(in_state(GameState::Loading)),
+                    launch_stuck_balls.run_if(in_state(GameState::Playing)),
+                ),
+            )
+            // runs once when the app starts
+            .add_systems(Startup, setup)
+            // OnEnter systems run once when the game switches into a state, OnExit systems run once when it leaves it
+            .add_systems(OnExit(GameState::Loading), despawn_screen::<OnLoadingScreen>)
+            // used for physics updates and other operations that should occur at a fixed interval.
+            // Many of these systems touch the same balls, bricks and resources. .chain() runs them one after the other
+            // in this order: without an order Bevy could run them differently every tick, and the same seed
+            // (see rng.rs) would not play the same game
+            .add_systems(
+                FixedUpdate,
+                (
+                    // power-ups, the paddle and everything that moves before the balls, see powerup.rs
+                    (
+                        tick_power_ups,
+                        apply_velocity,
+                        collect_power_ups,
+                        resize_paddle,
+                        move_paddle,
+                        hold_stuck_balls,
+                        fire_lasers,
+                    )
+                        .chain(),
+                    // the balls. check_ball_collisions moves them itself, so they can stop at the first thing they hit.
+                    // Bolts hit bricks through CollisionEvent, so they are checked before damage_bricks reads them
+                    (
+                        curve_balls,
+                        check_ball_collisions,
+                        collide_balls,
+                        check_laser_hits,
+                    )
+                        .chain(),
+                    // what the hits do to the bricks, the score and the power-ups
+                    (
+                        damage_bricks,
+                        update_score,
+                        update_brick_colors,
+                        regenerate_bricks,
+                        spawn_extra_balls,
+                        drop_power_ups,
+                    )
+                        .chain(),
+                    // what the hits do to the balls
+                    (
+                        stick_balls_to_paddle,
+                        spin_balls_off_paddle,
+                        prevent_ball_stall,
+                        speed_up_balls,
+                        apply_ball_speed,
+                    )
+                        .chain(),
+                    // the end of the life or of the level, once everything else is done
+                    (check_ball_lost, check_level_cleared).chain(),
+                )
+                    .chain()
+                    // the ball and paddle freeze in every other state (menu, paused, level cleared, game over)
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+// States describe which "screen" the game is currently on. Only one state is active at a time.
+// Systems can be told to only run in a given state with .run_if(in_state(...)).
+#[derive(States, Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+enum GameState {
+    // the game starts by waiting for the level files to be loaded
+    #[default]
+    Loading,
+    MainMenu,
+    Playing,
+    // the round is frozen until the player resumes
+    Paused,
+    // every brick of the current level is destroyed, waiting for the player to start the next one
+    LevelCleared,
+    // the run is over (all lives lost or the last level cleared) with a score good enough for the high score table,
+    // the player types their name
+    NewHighScore,
+    // the run is over, the final score is shown
+    GameOver,
+}
+
+// Marker components: every entity spawned for a screen gets one of these,
+// so the whole screen can be removed at once with despawn_screen::<Marker>
+#[derive(Component)]
+struct OnGameScreen;
+
+#[derive(Component)]
+struct OnLoadingScreen;
+
+#[derive(Component)]
+struct OnNoLevelsScreen;
+
+#[derive(Component)]
+struct OnMainMenuScreen;
+
+#[derive(Component)]
+struct OnPauseScreen;
+
+#[derive(Component)]
+struct OnLevelClearedScreen;
+
+#[derive(Component)]
+struct OnGameOverScreen;
+
 #[derive(Component)]
 struct Paddle;
 
 #[derive(Component)]
-// to access the size of a ball, use ball.size
+// the ball collides as a circle, like the circle.png texture it is drawn with
+// to access the radius of a ball, use ball.radius
 struct Ball {
-    size: Vec2,
+    radius: f32,
 }
 
 // Deref and DerefMut are standard Rust traits. Deref allows a type to behave like a reference to another type, and DerefMut is its mutable counterpart.
@@ -105,6 +375,18 @@ struct Ball {
 // this is a tuple struct, to accessVec2 in Velocity, we need to used index-based access like velocity.0
 struct Velocity(Vec2);
 
+// how fast the direction of the ball turns, in radians per second (positive turns it to the left), see curve_balls
+#[derive(Component, Default)]
+struct Spin {
+    rate: f32,
+}
+
+// how long (in seconds) the ball has been moving almost straight along an axis, see prevent_ball_stall
+#[derive(Component, Default)]
+struct StallTimer {
+    seconds: f32,
+}
+
 #[derive(Component)]
 struct Collider {
     size: Vec2,
@@ -127,6 +409,24 @@ struct WallBundle {
 struct Brick {
     // i8 = small integer - indicates how much damage the brick can withstand before breaking.
     health: i8,
+    // the health the brick was spawned with
+    max_health: i8,
+    // points for every hit, and extra points for breaking it
+    hit_score: usize,
+    destroy_score: usize,
+    // what is special about the brick (steel, explosive, ...), see BrickKind
+    kind: BrickKind,
+}
+
+// a broken regenerating brick: it is hidden and has no Collider until the timer finishes
+#[derive(Component)]
+struct RegenerationTimer(Timer);
+
+// a brick fades from full_health to last_hit as it loses health, see update_brick_colors
+#[derive(Component)]
+struct BrickColors {
+    full_health: Color,
+    last_hit: Color,
 }
 
 // Resources are global data accessible throughout your game, like a global score or game settings. 
@@ -136,97 +436,171 @@ struct Scoreboard {
     score: usize,
 }
 
-// Useful for global data that needs a default state and direct access to inner data.
-#[derive(Resource, Default, Deref, DerefMut)]
-struct CollisionSound(Handle<AudioSource>);
+// number of balls the player can still lose before the game is over
+#[derive(Resource, Clone, Copy)]
+struct Lives {
+    remaining: usize,
+}
+
+// which level from Levels is being played (0 is the first one)
+#[derive(Resource, Clone, Copy)]
+struct CurrentLevel {
+    index: usize,
+    // the score when the level was started, restarting the level from the pause menu goes back to it
+    starting_score: usize,
+}
+
+// what kind of thing the ball bounced off
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum CollisionSurface {
+    Paddle,
+    Wall,
+    Brick,
+    // another ball, see collide_balls
+    Ball,
+}
+
+// sent by check_ball_collisions and collide_balls every time a ball bounces off something,
+// and by check_laser_hits when a laser bolt hits a brick
+#[derive(Event)]
+struct CollisionEvent {
+    // the ball (or laser bolt)
+    ball: Entity,
+    // the paddle, wall, brick or other ball that was hit
+    other: Entity,
+    surface: CollisionSurface,
+    // the direction pointing straight out of the side that was hit, e.g. Vec2::Y when the ball landed on top of it
+    normal: Vec2,
+    // where the center of the ball was when it touched
+    position: Vec2,
+}
+
+// sent by damage_bricks every time a brick is hit by a ball or caught in an explosion
+#[derive(Event)]
+struct BrickHitEvent {
+    kind: BrickKind,
+    // true when the brick has no health left and is being despawned (or hidden, for a regenerating brick)
+    destroyed: bool,
+    // what the hit is worth: the brick's hit_score, plus its destroy_score when it broke
+    points: usize,
+    // the center of the brick
+    position: Vec2,
+}
+
+// marks the text entity showing the score and lives, so update_scoreboard doesn't pick up other texts
+#[derive(Component)]
+struct ScoreboardText;
 
 fn setup(
     // commands is used to spawn entities (like the camera, paddle, balls, walls) and insert resources (like sounds).
     mut commands: Commands,
     // provides access to the functionality needed to load external assets, like audio and images, into the game 
-    asset_server: Res<AssetServer>
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
     ) {
     //camera
     commands.spawn(Camera2dBundle::default());
+    commands.insert_resource(ClearColor(config.background_color));
+
+    //lives
+    // the player starts with starting_lives balls, one is lost every time the ball falls below the paddle
+    commands.insert_resource(Lives {
+        remaining: config.starting_lives,
+    });
+
+    //levels
+    // starts loading every level file in assets/levels/, check_levels_loaded waits for them to be ready
+    match asset_server.load_folder("levels") {
+        Ok(folder) => {
+            let mut handles: Vec<Handle<Level>> =
+                folder.into_iter().map(|handle| handle.typed()).collect();
+            // levels are played in file name order (01.level.ron, 02.level.ron, ...)
+            handles.sort_by_key(|handle| {
+                asset_server
+                    .get_handle_path(handle)
+                    .map(|path| path.path().to_path_buf())
+            });
+            commands.insert_resource(Levels { handles });
+        }
+        Err(err) => error!("could not read the levels folder: {err}"),
+    }
+    spawn_menu_text(&mut commands, OnLoadingScreen, "LOADING", "");
+}
 
-    //sound
-    let ball_collision_sound = asset_server.load("sounds/breakout_collision.ogg");
-    commands.insert_resource(CollisionSound(ball_collision_sound));
+// spawns everything a round is played with: paddle, ball, walls, bricks and the scoreboard.
+// Every entity is tagged with OnGameScreen so the round can be thrown away and started again.
+#[allow(clippy::too_many_arguments)]
+fn spawn_playfield(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
+    current_level: Res<CurrentLevel>,
+    levels: Res<Levels>,
+    level_assets: Res<Assets<Level>>,
+    mut power_ups: ResMut<ActivePowerUps>,
+    mut ball_speed: ResMut<BallSpeed>,
+    mut rng: ResMut<GameRng>,
+) {
+    let level = levels
+        .get(current_level.index, &level_assets)
+        .expect("the game only leaves the Loading state once levels are loaded");
+    // power-ups caught and speed gained in the last round don't carry over
+    power_ups.clear();
+    ball_speed.reset(level.ball_speed, config.ball_max_speed);
 
     //paddle
     commands.spawn((
         // set up the visual appearance of the paddle
         SpriteBundle {
             transform: Transform {
-                translation: vec3(0., PADDLE_START_Y, 0.),
+                translation: vec3(0., config.paddle_start_y, 0.),
                 ..default()
             },
             sprite: Sprite {
-                color: PADDLE_COLOR,
-                custom_size: Some(PADDLE_SIZE),
+                color: config.paddle_color,
+                custom_size: Some(config.paddle_size),
                 ..default()
             },
             ..default()
         },
         Paddle,
-        Collider { size: PADDLE_SIZE },
+        Collider {
+            size: config.paddle_size,
+        },
+        // only used to know how fast the paddle is moving, move_paddle moves it (not apply_velocity)
+        Velocity(Vec2::ZERO),
+        OnGameScreen,
     ));
 
     //ball
-    let ball_tex = asset_server.load("textures/circle.png");
-    
-    // Initialize the random number generator using thread_rng function
-    let mut rng = thread_rng();
-
-    // for _ in 0..1_000 {
-        // Generate random initial direction
-        // Generates a random floating-point number between 0.0 and approximately 6.28 (2 pi)
-        let random_angle = rng.gen_range(0.0..std::f32::consts::TAU); // TAU is 2*PI = represnet a full rotation in radians
-        // set random movement direction for an object in a game
-        let random_direction = Vec2::new(random_angle.cos(), random_angle.sin());
-        let random_color = Color::rgba(
-            rng.gen_range(0.0..1.0),
-            rng.gen_range(0.0..1.0),
-            rng.gen_range(0.0..1.0),
-            1.0, // alpha value, you can randomize this too if you want
-        );
-
-        commands.spawn((
-            SpriteBundle {
-                transform: Transform {
-                    translation: BALL_STARTING_POSITION,
-                    ..Default::default()
-                },
-                sprite: Sprite {
-                    color: random_color,
-                    custom_size: Some(BALL_SIZE),
-                    ..Default::default()
-                },
-                // creates a copy of a value
-                // create a new instance of the texture handle ball_tex. 
-                // This is necessary because you're using the texture for multiple sprites, and each sprite needs its own handle to the texture.
-                texture: ball_tex.clone(),
-                ..Default::default()
-            },
-            Ball { size: BALL_SIZE },
-            // have both magnitude and direction
-            Velocity(BALL_SPEED * random_direction),
-        ));
-    
+    // a round starts with one ball, more are added during the round by extra-ball bricks and the MultiBall power-up
+    // it doesn't move until it is served from the paddle, see serve.rs
+    let ball = spawn_ball(
+        &mut commands,
+        &asset_server,
+        &config,
+        &mut *rng,
+        config.ball_starting_position(),
+        Vec2::ZERO,
+    );
+    commands.entity(ball).insert(StuckToPaddle::serve());
 
     //walls
     {
-        let vertical_wall_size = vec2(WALL_THICKNESS, WALL_BLOCK_HEIGHT + WALL_THICKNESS);
-        let horizontal_wall_size = vec2(WALL_BLOCK_WIDTH + WALL_THICKNESS, WALL_THICKNESS);
+        // total width and height of the game area enclosed by the walls
+        let wall_block_width = config.right_wall - config.left_wall;
+        let wall_block_height = config.top_wall - config.bottom_wall;
+        let vertical_wall_size = vec2(config.wall_thickness, wall_block_height + config.wall_thickness);
+        let horizontal_wall_size = vec2(wall_block_width + config.wall_thickness, config.wall_thickness);
         //left wall
         commands.spawn(WallBundle {
             sprite_bundle: SpriteBundle {
                 transform: Transform {
-                    translation: vec3(LEFT_WALL, 0.0, 0.0),
+                    translation: vec3(config.left_wall, 0.0, 0.0),
                     ..default()
                 },
                 sprite: Sprite {
-                    color: WALL_COLOR,
+                    color: config.wall_color,
                     // In Rust, Option is a special type used for values that can either be something (Some) or nothing (None). It's commonly used when a value may or may not be present.
                     // Unlike some other programming languages that use null values, Rust uses Option to handle the absence of a value more safely and clearly
                     // The custom_size field is designed to optionally accept a size.
@@ -242,17 +616,18 @@ fn setup(
             collider: Collider {
                 size: vertical_wall_size,
             },
-        });
+        })
+        .insert(OnGameScreen);
 
         //right wall
         commands.spawn(WallBundle {
             sprite_bundle: SpriteBundle {
                 transform: Transform {
-                    translation: vec3(RIGHT_WALL, 0.0, 0.0),
+                    translation: vec3(config.right_wall, 0.0, 0.0),
                     ..default()
                 },
                 sprite: Sprite {
-                    color: WALL_COLOR,
+                    color: config.wall_color,
                     custom_size: Some(vertical_wall_size),
                     ..default()
                 },
@@ -261,17 +636,20 @@ fn setup(
             collider: Collider {
                 size: vertical_wall_size,
             },
-        });
+        })
+        .insert(OnGameScreen);
 
-        //bottom wall
+        // there is no bottom wall: the bottom edge is where the ball is lost (see check_ball_lost)
+
+        //top wall
         commands.spawn(WallBundle {
             sprite_bundle: SpriteBundle {
                 transform: Transform {
-                    translation: vec3(0.0, BOTTOM_WALL, 0.0),
+                    translation: vec3(0.0, config.top_wall, 0.0),
                     ..default()
                 },
                 sprite: Sprite {
-                    color: WALL_COLOR,
+                    color: config.wall_color,
                     custom_size: Some(horizontal_wall_size),
                     ..default()
                 },
@@ -280,140 +658,259 @@ fn setup(
             collider: Collider {
                 size: horizontal_wall_size,
             },
-        });
+        })
+        .insert(OnGameScreen);
+    }
 
-        //top wall
-        commands.spawn(WallBundle {
-            sprite_bundle: SpriteBundle {
+    //bricks
+    spawn_level_bricks(&mut commands, &config, level);
+
+    //Scoreboard
+    // TextBundle - A bundle of components used in Bevy for creating text-based UI elements.
+    // TextBundle::from_sections is a function used to create text entities that consist of multiple parts or "sections." 
+    commands.spawn((
+        TextBundle::from_sections([
+            // This part creates the pieces of text.
+            // First Piece ("Score: "): This is just the word "Score: ".
+            TextSection::new(
+                "Score: ",
+                TextStyle {
+                    font_size: SCOREBOARD_FONT_SIZE,
+                    color: TEXT_COLOR,
+                    ..default()
+                },
+            ),
+            TextSection::from_style(TextStyle {
+                font_size: SCOREBOARD_FONT_SIZE,
+                color: SCORE_COLOR,
+                ..default()
+            }),
+            // Third and fourth piece: "  Lives: " followed by the number of lives left
+            TextSection::new(
+                "  Lives: ",
+                TextStyle {
+                    font_size: SCOREBOARD_FONT_SIZE,
+                    color: TEXT_COLOR,
+                    ..default()
+                },
+            ),
+            TextSection::from_style(TextStyle {
+                font_size: SCOREBOARD_FONT_SIZE,
+                color: SCORE_COLOR,
+                ..default()
+            }),
+            // Fifth and sixth piece: "  Level: " followed by the level number
+            TextSection::new(
+                "  Level: ",
+                TextStyle {
+                    font_size: SCOREBOARD_FONT_SIZE,
+                    color: TEXT_COLOR,
+                    ..default()
+                },
+            ),
+            TextSection::from_style(TextStyle {
+                font_size: SCOREBOARD_FONT_SIZE,
+                color: SCORE_COLOR,
+                ..default()
+            }),
+        ])
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: SCOREBOARD_TEXT_PADDING,
+            left: SCOREBOARD_TEXT_PADDING,
+            ..default()
+        }),
+        ScoreboardText,
+        OnGameScreen,
+    ));
+}
+
+// spawns every brick of `level` at full health
+fn spawn_level_bricks(commands: &mut Commands, config: &GameConfig, level: &Level) {
+    for level_brick in &level.bricks {
+        spawn_brick(
+            commands,
+            config,
+            config.brick_position(level_brick.row, level_brick.column),
+            Brick {
+                health: level_brick.health,
+                max_health: level_brick.health,
+                hit_score: level_brick.hit_score,
+                destroy_score: level_brick.destroy_score,
+                kind: level_brick.kind,
+            },
+            BrickColors {
+                full_health: level_brick.color,
+                last_hit: level_brick.damaged_color,
+            },
+        );
+    }
+}
+
+// spawns a brick at `position`, colored for the health it has (update_brick_colors keeps the color up to date after that)
+fn spawn_brick(
+    commands: &mut Commands,
+    config: &GameConfig,
+    position: Vec2,
+    brick: Brick,
+    colors: BrickColors,
+) -> Entity {
+    commands
+        .spawn((
+            SpriteBundle {
                 transform: Transform {
-                    translation: vec3(0.0, TOP_WALL, 0.0),
+                    // extend(0.0) adds a z-coordinate (depth), which is required for a 3D transform but typically 0.0 in 2D games
+                    translation: position.extend(0.0),
                     ..default()
                 },
                 sprite: Sprite {
-                    color: WALL_COLOR,
-                    custom_size: Some(horizontal_wall_size),
+                    color: colors.full_health,
+                    custom_size: Some(config.brick_size),
                     ..default()
                 },
                 ..default()
             },
-            collider: Collider {
-                size: horizontal_wall_size,
+            brick,
+            colors,
+            // a Collider is used to define the physical shape of an entity for the purpose of collision detection
+            // Static by Default: Without additional components, a Collider in Bevy doesn't make an entity dynamic. It means that the entity won't move or react to physical forces on its own; it just has a defined shape for collision purposes.
+            Collider {
+                size: config.brick_size,
             },
-        });
-    }
+            OnGameScreen,
+        ))
+        .id()
+}
 
-    //bricks
-    {
-        let offset_x = LEFT_WALL + GAP_BETWEEN_BRICKS_AND_SIDES + BRICK_SIZE.x * 0.5;
-        let offset_y = BOTTOM_WALL + GAP_BETWEEN_PADDLE_AND_BRICKS + BRICK_SIZE.y * 0.5;
-
-        let bricks_total_width = (RIGHT_WALL - LEFT_WALL) - 2. * GAP_BETWEEN_BRICKS_AND_SIDES;
-        let bricks_total_height = (TOP_WALL - BOTTOM_WALL)
-            - GAP_BETWEEN_BRICKS_AND_CEILING
-            - GAP_BETWEEN_PADDLE_AND_BRICKS;
-
-        // floor() rounds down the result to the nearest whole number
-        // i32 converts the result to a 32-bit integer
-        let rows = (bricks_total_height / (BRICK_SIZE.y + GAP_BETWEEN_BRICKS)).floor() as i32;
-        let columns = (bricks_total_width / (BRICK_SIZE.x + GAP_BETWEEN_BRICKS)).floor() as i32;
-
-        for row in 0..rows {
-            for column in 0..columns {
-                let brick_pos = vec2(
-                    // column as f32: This converts the column number (which is an integer) to a floating-point number
-                    offset_x + column as f32 * (BRICK_SIZE.x + GAP_BETWEEN_BRICKS),
-                    offset_y + row as f32 * (BRICK_SIZE.y + GAP_BETWEEN_BRICKS),
-                );
+// spawns a ball with a random color at `position`, moving with `velocity`
+fn spawn_ball(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    config: &GameConfig,
+    rng: &mut impl Rng,
+    position: Vec3,
+    velocity: Vec2,
+) -> Entity {
+    // loading the same file again gives back the same texture handle, it isn't loaded twice
+    let ball_tex = asset_server.load("textures/circle.png");
 
-                commands.spawn((
-                    SpriteBundle {
-                        transform: Transform {
-                            // extend(0.0) adds a z-coordinate (depth), which is required for a 3D transform but typically 0.0 in 2D games
-                            translation: brick_pos.extend(0.0),
-                            ..default()
-                        },
-                        sprite: Sprite {
-                            color: BRICK_COLOR,
-                            custom_size: Some(BRICK_SIZE),
-                            ..default()
-                        },
-                        ..default()
-                    },
-                    Brick { health: 1 },
-                    // a Collider is used to define the physical shape of an entity for the purpose of collision detection
-                    // Static by Default: Without additional components, a Collider in Bevy doesn't make an entity dynamic. It means that the entity won't move or react to physical forces on its own; it just has a defined shape for collision purposes.
-                    Collider { size: BRICK_SIZE },
-                ));
-            }
-        }
-    }
+    let random_color = Color::rgba(
+        rng.gen_range(0.0..1.0),
+        rng.gen_range(0.0..1.0),
+        rng.gen_range(0.0..1.0),
+        1.0, // alpha value, you can randomize this too if you want
+    );
 
-    //Scoreboard
-    // TextBundle - A bundle of components used in Bevy for creating text-based UI elements.
-    // TextBundle::from_sections is a function used to create text entities that consist of multiple parts or "sections." 
-    commands.spawn((TextBundle::from_sections([
-        // This part creates two pieces of text.
-        // First Piece ("Score: "): This is just the word "Score: ".
-        TextSection::new(
-            "Score: ",
-            TextStyle {
-                font_size: SCOREBOARD_FONT_SIZE,
-                color: TEXT_COLOR,
-                ..default()
+    commands
+        .spawn((
+            SpriteBundle {
+                transform: Transform {
+                    translation: position,
+                    ..Default::default()
+                },
+                sprite: Sprite {
+                    color: random_color,
+                    custom_size: Some(config.ball_size),
+                    ..Default::default()
+                },
+                texture: ball_tex,
+                ..Default::default()
             },
-        ),
-        TextSection::from_style(TextStyle {
-            font_size: SCOREBOARD_FONT_SIZE,
-            color: SCORE_COLOR,
-            ..default()
-        }),
-    ])
-    .with_style(Style {
-        position_type: PositionType::Absolute,
-        top: SCOREBOARD_TEXT_PADDING,
-        left: SCOREBOARD_TEXT_PADDING,
-        ..default()
-    }),));
+            Ball {
+                radius: config.ball_size.x * 0.5,
+            },
+            Velocity(velocity),
+            StallTimer::default(),
+            Spin::default(),
+            OnGameScreen,
+        ))
+        .id()
+}
+
+// picks a random direction (a vector of length 1) for the ball to be launched in,
+// upward and tilted between ball_launch_min_angle and ball_launch_max_angle to the left or right
+fn random_ball_direction(config: &GameConfig, rng: &mut impl Rng) -> Vec2 {
+    let random_angle = rng.gen_range(config.ball_launch_min_angle..=config.ball_launch_max_angle);
+    // gen_bool(0.5) is a coin flip: tilt to the left or to the right
+    let side = if rng.gen_bool(0.5) { -1.0 } else { 1.0 };
+    // set random movement direction for an object in a game
+    tilted_up(side * random_angle)
+}
+
+// the direction (a vector of length 1) that is `angle` radians away from straight up, to the right for a positive angle.
+// sin gives the sideways part and cos the upward part
+fn tilted_up(angle: f32) -> Vec2 {
+    Vec2::new(angle.sin(), angle.cos())
 }
 
 fn move_paddle(
-    // allows the function to access player input. It checks which keys are pressed.
-    input: Res<Input<KeyCode>>,
+    // the paddle speed, momentum and the position of the walls, see config.rs
+    config: Res<GameConfig>,
+    // allows the function to access player input: which way the player wants the paddle to go, see controls.rs
+    actions: Res<InputActions>,
     // provides access to the game's timing information, like the duration of the current frame. 
     time_step: Res<FixedTime>,
     // find the entity that represents the paddle and get its Transform component
-    mut query: Query<&mut Transform, With<Paddle>>,
+    // the Collider gives its current size, which changes with the WidePaddle power-up
+    mut query: Query<(&mut Transform, &mut Velocity, &Collider), With<Paddle>>,
 ) {
     // gets the Transform component of the paddle entity
-    let mut paddle_transform = query.single_mut();
+    let (mut paddle_transform, mut paddle_velocity, paddle_collider) = query.single_mut();
+    let paddle_width = paddle_collider.size.x;
+    let dt = time_step.period.as_secs_f32();
 
-    let mut direction = 0.0;
-    if input.pressed(KeyCode::A) {
-        direction -= 1.0;
-    }
-    if input.pressed(KeyCode::D) {
-        direction += 1.0;
+    // keys and the stick give the direction directly. The mouse gives a position instead: the paddle heads there
+    // at up to full speed, and slows down at the end so it stops right on it instead of going past
+    if let Some(target_x) = actions.paddle_target.filter(|_| actions.movement == 0.0) {
+        let max_step = config.paddle_speed * dt;
+        let direction = ((target_x - paddle_transform.translation.x) / max_step).clamp(-1.0, 1.0);
+        paddle_velocity.x = direction * config.paddle_speed;
+    } else if config.paddle_momentum {
+        // speed up towards the wanted speed while a direction is held, slow down to a stop when nothing is
+        let (wanted_speed, change) = if actions.movement != 0.0 {
+            (
+                actions.movement * config.paddle_speed,
+                config.paddle_acceleration * dt,
+            )
+        } else {
+            (0.0, config.paddle_friction * dt)
+        };
+        // moves the speed by at most `change` towards the wanted speed, without going past it
+        paddle_velocity.x += (wanted_speed - paddle_velocity.x).clamp(-change, change);
+    } else {
+        paddle_velocity.x = actions.movement * config.paddle_speed;
     }
 
     // calculates the new horizontal position (x coordinate) for the paddle. 
-    // time_step.period.as_secs_f32(): This gives the duration of the current frame in seconds as a floating-point number. In other words, it tells you how much time has passed since the last frame.
-    let mut new_x =
-        // multiply PADDLE_SPEED (how fast) by time_step.period.as_secs_f32() (how much time has passed) to calculate how far the paddle should move in this specific frame.
-        // For example, if PADDLE_SPEED is 100 units/second, and your frame time is 1/60th of a second, then in one frame, the paddle should move 100×1/60units.
-        paddle_transform.translation.x + direction * PADDLE_SPEED * time_step.period.as_secs_f32();
+    // dt: This gives the duration of the current tick in seconds as a floating-point number. In other words, it tells you how much time has passed since the last tick.
+    // multiply the speed of the paddle by dt (how much time has passed) to calculate how far the paddle should move in this specific tick.
+    // For example, if the paddle moves at 100 units/second, and a tick is 1/60th of a second, then in one tick, the paddle should move 100×1/60units.
+    let mut new_x = paddle_transform.translation.x + paddle_velocity.x * dt;
 
     // ensure that the paddle doesn't move beyond the boundaries of the game area
     // The min function is used to compare the current new_x value with the calculated right boundary position
     // If new_x is less than the right boundary (meaning the paddle is within the bounds), new_x remains unchanged.
     // If new_x is greater (meaning the paddle would go past the right boundary), new_x is set to the right boundary value to prevent it from going too far.
-    new_x = new_x.min(RIGHT_WALL - (WALL_THICKNESS + PADDLE_SIZE.x) * 0.5);
-    new_x = new_x.max(LEFT_WALL + (WALL_THICKNESS + PADDLE_SIZE.x) * 0.5);
+    new_x = new_x.min(config.right_wall - (config.wall_thickness + paddle_width) * 0.5);
+    new_x = new_x.max(config.left_wall + (config.wall_thickness + paddle_width) * 0.5);
 
+    // a paddle stopped by a wall loses its speed, so it doesn't keep pushing against it
+    if new_x != paddle_transform.translation.x + paddle_velocity.x * dt {
+        paddle_velocity.x = 0.0;
+    }
     paddle_transform.translation.x = new_x;
 }
 
+// a query filter for what apply_velocity moves, see below
+type MovedByVelocity = (Without<Ball>, Without<Paddle>);
+
 // applies to all entities in your game world that have both a Transform component and a Velocity component.
-fn apply_velocity(mut query: Query<(&mut Transform, &Velocity)>, time_step: Res<FixedTime>) {
+// Balls are left out: they are moved step by step in check_ball_collisions, so they can't pass through anything.
+// The paddle is left out too: move_paddle moves it, so it can stop at the walls.
+fn apply_velocity(
+    mut query: Query<(&mut Transform, &Velocity), MovedByVelocity>,
+    time_step: Res<FixedTime>,
+) {
     // dt (delta time) holds the amount of time that has passed since the last frame/update
     let dt = time_step.period.as_secs_f32();
     for (mut transform, velocity) in &mut query {
@@ -423,93 +920,797 @@ fn apply_velocity(mut query: Query<(&mut Transform, &Velocity)>, time_step: Res<
     }
 }
 
+// everything a ball can bounce off, for check_ball_collisions.
+// Entity: This retrieves the entity's ID, which is sent along with the CollisionEvent
+// Option<&Brick>- This is an optional component. It means this query will include entities even if they don't have a Brick component.
+// Option<&Paddle> is only Some for the paddle, it gets its own bounce rule
+// Without<Ball> tells Bevy this query never touches the Transform of a ball, so both queries can be used at the same time
+type ColliderQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Transform,
+        &'static Collider,
+        Option<&'static Brick>,
+        Option<&'static Paddle>,
+    ),
+    Without<Ball>,
+>;
+
+// Moves every ball for this tick and bounces it off whatever it hits on the way.
+// Only the first contact is handled at a time: the ball moves up to it, bounces, and carries on with the rest of
+// its motion. This way a fast ball can't pass through a brick, and hitting two bricks at once only bounces it once.
+// What happens to the thing that was hit (damage, score, sound, ...) is left to the systems reading CollisionEvent.
 fn check_ball_collisions(
-    mut commands: Commands,
-    mut score: ResMut<Scoreboard>,
-    collision_sound: Res<CollisionSound>,
+    // the paddle bounce angle, see paddle_bounce_velocity
+    config: Res<GameConfig>,
+    mut collision_events: EventWriter<CollisionEvent>,
+    time_step: Res<FixedTime>,
+    power_ups: Res<ActivePowerUps>,
     // get entities that have all three components: Velocity, Transform, and Ball.
-    mut ball_query: Query<(&mut Velocity, &Transform, &Ball)>,
-    // Entity: This retrieves the entity's ID. It's useful for performing operations on the entity itself, like despawning
-    // Option<&mut Brick>- This is an optional component. It means this query will include entities even if they don't have a Brick component.
-    // If an entity has a Brick component, it provides mutable access to it, allowing you to modify the Brick (like changing its health).
-    mut collider_query: Query<(Entity, &Transform, &Collider, Option<&mut Brick>)>, // Note the mutability for Brick
+    // balls held by the sticky paddle don't move on their own, hold_stuck_balls moves them with the paddle
+    mut ball_query: Query<(Entity, &mut Velocity, &mut Transform, &Ball), Without<StuckToPaddle>>,
+    collider_query: ColliderQuery,
 ) {
-    for (mut ball_velocity, ball_transform, ball) in &mut ball_query {
-        for (other_entity, transform, other, opt_brick) in &mut collider_query {
-            // The bevy::sprite::collide_aabb::collide function in Rust performs simple AABB collision detection
-            // pub fn collide(
-            //     a_pos: Vec3,
-            //     a_size: Vec2,
-            //     b_pos: Vec3,
-            //     b_size: Vec2
-            // ) -> Option<Collision>
-            // 1. Determine the distance between the centers of the two entities.
-            // 2. Compare this distance to the combined sizes of the entities. For circular objects, this would be the radii; for rectangular objects, you might use half the width/height.
-            // 3. If the distance is less than the combined sizes, a collision is occurring.
-            let collision = collide(
-                // Position of the First Entity - The current position of the ball
-                ball_transform.translation,
-                // Size of the First Entity
-                ball.size,
-                // Position of the Second Entity - The position of the other entity (like a brick or wall)
-                transform.translation,
-                // Size of the Second Entity - The size of the other entity.
-                other.size,
-            );
+    // with the SlowBall power-up the balls cover less ground per tick, their velocity itself doesn't change
+    let dt = time_step.period.as_secs_f32() * power_ups.ball_speed_scale();
 
-            let mut reflect_x = false;
-            let mut reflect_y = false;
-            // If a collision is detected, this block determines from which side the collision occurred (left, right, top, bottom, or inside).
-            if let Some(collision) = collision {
-                match collision {
-                    // If the ball hits something on its left side, check if the ball is moving to the right (ball_velocity.x > 0.0). 
-                    // If it is, set reflect_x to true. 
-                    Collision::Left => reflect_x = ball_velocity.x > 0.0,
-                    // If the ball hits something on its right side, check if the ball is moving to the left (ball_velocity.x < 0.0). 
-                    // If it is, set reflect_x to true.
-                    Collision::Right => reflect_x = ball_velocity.x < 0.0,
-                    Collision::Top => reflect_y = ball_velocity.y < 0.0,
-                    Collision::Bottom => reflect_y = ball_velocity.y > 0.0,
-                    Collision::Inside => { /* do nothing */ }
-                }
+    for (ball_entity, mut ball_velocity, mut ball_transform, ball) in &mut ball_query {
+        let mut position = ball_transform.translation.truncate();
+        // the part of this tick the ball still has to travel: 1.0 is all of it
+        let mut remaining = 1.0;
+
+        for _ in 0..MAX_BALL_CONTACTS_PER_TICK {
+            let motion = ball_velocity.0 * dt * remaining;
+
+            // 1. if the ball is stuck inside something (e.g. the paddle moved into it), that is handled first.
+            // 2. otherwise, the first thing on the ball's path is hit.
+            let mut first_contact: Option<(Entity, Contact)> = None;
+            let mut push_out = Vec2::ZERO;
+            for (other_entity, transform, other, _, _) in &collider_query {
+                let other_center = transform.translation.truncate();
+                let other_half_size = other.size * 0.5;
 
-                if reflect_x {
-                // If the ball should bounce (for example, it hit the left side and was moving right), reflect_x is set to true.
-                // When reflect_x is true, the code then reverses the ball's horizontal velocity (ball_velocity.x *= -1;). 
-                // This reversal makes the ball start moving in the opposite direction, simulating a bounce.
-                    ball_velocity.x *= -1.;
+                if let Some(push) =
+                    circle_penetration(position, ball.radius, other_center, other_half_size)
+                {
+                    push_out = push;
+                    first_contact = Some((
+                        other_entity,
+                        Contact {
+                            time: 0.0,
+                            normal: push.normalize(),
+                        },
+                    ));
+                    break;
                 }
-                if reflect_y {
-                    ball_velocity.y *= -1.;
+
+                if let Some(contact) =
+                    sweep_circle(position, ball.radius, motion, other_center, other_half_size)
+                {
+                    // keep the contact that happens earliest
+                    if first_contact
+                        .as_ref()
+                        .is_none_or(|(_, first)| contact.time < first.time)
+                    {
+                        first_contact = Some((other_entity, contact));
+                    }
                 }
+            }
+
+            // nothing in the way: the ball travels the rest of its motion
+            let Some((other_entity, contact)) = first_contact else {
+                position += motion;
+                break;
+            };
+
+            // move the ball up to the contact point (or out of what it was stuck in)
+            position += push_out + motion * contact.time;
+            remaining *= 1.0 - contact.time;
+
+            // only a ball moving into the surface bounces; one that is already moving away was just pushed out
+            let speed_into_surface = ball_velocity.dot(contact.normal);
+            if speed_into_surface >= 0.0 {
+                continue;
+            }
+            // reflect the velocity on the surface that was hit: the part going into the surface is reversed,
+            // the part going along it is kept. For a wall this is the same as ball_velocity.x *= -1,
+            // for the corner of a brick the ball bounces off along the direction pointing away from the corner.
+            ball_velocity.0 -= 2.0 * speed_into_surface * contact.normal;
+
+            let Ok((_, transform, other, opt_brick, opt_paddle)) = collider_query.get(other_entity) else {
+                continue;
+            };
+
+            // a ball landing on top of the paddle is aimed instead of simply bounced, see paddle_bounce_velocity
+            if opt_paddle.is_some() && contact.normal == Vec2::Y {
+                ball_velocity.0 = paddle_bounce_velocity(
+                    &config,
+                    ball_velocity.length(),
+                    position.x,
+                    transform.translation.x,
+                    other.size.x,
+                );
+            }
+
+            let surface = if opt_paddle.is_some() {
+                CollisionSurface::Paddle
+            } else if opt_brick.is_some() {
+                CollisionSurface::Brick
+            } else {
+                CollisionSurface::Wall
+            };
+            collision_events.send(CollisionEvent {
+                ball: ball_entity,
+                other: other_entity,
+                surface,
+                normal: contact.normal,
+                position,
+            });
+        }
+
+        ball_transform.translation = position.extend(ball_transform.translation.z);
+    }
+}
+
+// Balls bounce off each other like billiard balls: they all weigh the same, so the parts of their velocities
+// along the line between their centers are swapped, and the parts across it are kept.
+// This runs after every ball has been moved for the tick, and pushes overlapping balls apart.
+fn collide_balls(
+    mut collision_events: EventWriter<CollisionEvent>,
+    mut ball_query: Query<(Entity, &mut Velocity, &mut Transform, &Ball), Without<StuckToPaddle>>,
+) {
+    // iter_combinations_mut goes through every pair of balls once
+    let mut pairs = ball_query.iter_combinations_mut();
+    while let Some([a, b]) = pairs.fetch_next() {
+        let (entity_a, mut velocity_a, mut transform_a, ball_a) = a;
+        let (entity_b, mut velocity_b, mut transform_b, ball_b) = b;
+
+        let offset = (transform_b.translation - transform_a.translation).truncate();
+        let distance = offset.length();
+        let overlap = ball_a.radius + ball_b.radius - distance;
+        // not touching, or exactly on top of each other (no way to tell which way to push them)
+        if overlap <= 0.0 || distance == 0.0 {
+            continue;
+        }
+        // points from ball a to ball b
+        let normal = offset / distance;
+
+        // each ball is pushed back half of the overlap, so they only just touch
+        transform_a.translation -= (normal * overlap * 0.5).extend(0.0);
+        transform_b.translation += (normal * overlap * 0.5).extend(0.0);
+
+        // how fast they move towards each other along the normal, balls already moving apart don't bounce
+        let closing_speed = (velocity_a.0 - velocity_b.0).dot(normal);
+        if closing_speed <= 0.0 {
+            continue;
+        }
+        velocity_a.0 -= closing_speed * normal;
+        velocity_b.0 += closing_speed * normal;
+
+        let position = transform_a.translation.truncate() + normal * ball_a.radius;
+        collision_events.send(CollisionEvent {
+            ball: entity_a,
+            other: entity_b,
+            surface: CollisionSurface::Ball,
+            normal: -normal,
+            position,
+        });
+    }
+}
+
+// the bottom edge of the arena is a kill zone: a ball that falls below it costs the player a life
+#[allow(clippy::too_many_arguments)]
+fn check_ball_lost(
+    config: Res<GameConfig>,
+    mut lives: ResMut<Lives>,
+    score: Res<Scoreboard>,
+    high_scores: Res<HighScores>,
+    current_level: Res<CurrentLevel>,
+    levels: Res<Levels>,
+    level_assets: Res<Assets<Level>>,
+    // used to switch the game into the NewHighScore or GameOver state
+    mut next_state: ResMut<NextState<GameState>>,
+    mut power_ups: ResMut<ActivePowerUps>,
+    mut ball_speed: ResMut<BallSpeed>,
+    mut commands: Commands,
+    mut ball_query: Query<(Entity, &mut Transform, &mut Velocity, &Ball)>,
+) {
+    let mut balls_in_play = ball_query.iter().count();
+
+    for (ball_entity, mut ball_transform, mut ball_velocity, ball) in &mut ball_query {
+        // the ball is only lost once it is completely below the bottom edge
+        if ball_transform.translation.y + ball.radius > config.bottom_wall {
+            continue;
+        }
+
+        // an extra ball that falls out is simply removed, a life is only lost with the last ball
+        if balls_in_play > 1 {
+            commands.entity(ball_entity).despawn();
+            balls_in_play -= 1;
+            continue;
+        }
+
+        // saturating_sub stops at 0 instead of overflowing (usize can't be negative)
+        lives.remaining = lives.remaining.saturating_sub(1);
+        // losing a life also loses the power-ups
+        power_ups.clear();
+        if lives.remaining == 0 {
+            end_run(&mut next_state, &high_scores, &score);
+            return;
+        }
+
+        // put the ball back on the paddle, to be served again at the level's starting speed.
+        // It is moved right away, so it isn't lost again before hold_stuck_balls has picked it up
+        ball_transform.translation = config.ball_starting_position();
+        ball_velocity.0 = Vec2::ZERO;
+        commands.entity(ball_entity).insert(StuckToPaddle::serve());
+        ball_speed.reset(
+            levels
+                .get(current_level.index, &level_assets)
+                .map_or(config.ball_speed, |level| level.ball_speed),
+            config.ball_max_speed,
+        );
+    }
+}
+
+// A ball moving (almost) flat bounces between the side walls without ever coming down, and one moving (almost)
+// straight up and down can keep bouncing between the paddle and the ceiling. When that goes on for too long,
+// the ball is turned a little away from the axis. Its speed stays the same.
+fn prevent_ball_stall(
+    config: Res<GameConfig>,
+    time_step: Res<FixedTime>,
+    mut ball_query: Query<(&mut Velocity, &mut StallTimer), With<Ball>>,
+) {
+    for (mut ball_velocity, mut stall_timer) in &mut ball_query {
+        let speed = ball_velocity.length();
+        if speed == 0.0 {
+            continue;
+        }
+        let direction = ball_velocity.0 / speed;
+        // angle between the path of the ball and the horizontal: 0.0 is flat, PI / 2.0 is straight up or down
+        let angle_from_horizontal = direction.y.abs().asin();
+        let too_flat = angle_from_horizontal < config.ball_stall_angle;
+        let too_steep =
+            angle_from_horizontal > std::f32::consts::FRAC_PI_2 - config.ball_stall_angle;
+
+        if !too_flat && !too_steep {
+            stall_timer.seconds = 0.0;
+            continue;
+        }
+        stall_timer.seconds += time_step.period.as_secs_f32();
+        if stall_timer.seconds < config.ball_stall_time {
+            continue;
+        }
+
+        stall_timer.seconds = 0.0;
+        let new_angle = if too_flat {
+            config.ball_stall_nudge_angle
+        } else {
+            std::f32::consts::FRAC_PI_2 - config.ball_stall_nudge_angle
+        };
+        // signum() keeps the ball going the same way (left or right, up or down) as before
+        ball_velocity.0 = speed
+            * Vec2::new(
+                new_angle.cos() * direction.x.signum(),
+                new_angle.sin() * direction.y.signum(),
+            );
+    }
+}
+
+// the change of state happens before the next frame, OnEnter systems of the new state run then.
+// A score that makes it into the table asks for the player's name before the game over screen
+fn end_run(next_state: &mut NextState<GameState>, high_scores: &HighScores, score: &Scoreboard) {
+    if high_scores.qualifies(score.score) {
+        next_state.set(GameState::NewHighScore);
+    } else {
+        next_state.set(GameState::GameOver);
+    }
+}
+
+// the level is won once every brick has been destroyed, and clearing the last level wins the whole run.
+// Steel bricks can't be destroyed so they don't count, and neither do broken regenerating bricks (they have no Collider)
+fn check_level_cleared(
+    current_level: Res<CurrentLevel>,
+    levels: Res<Levels>,
+    score: Res<Scoreboard>,
+    high_scores: Res<HighScores>,
+    brick_query: Query<&Brick, With<Collider>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !brick_query
+        .iter()
+        .all(|brick| brick.kind == BrickKind::Steel)
+    {
+        return;
+    }
+    if levels.is_last(current_level.index) {
+        end_run(&mut next_state, &high_scores, &score);
+    } else {
+        next_state.set(GameState::LevelCleared);
+    }
+}
+
+// waits until every level file has been loaded (or has failed to load) before showing the main menu
+fn check_levels_loaded(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut levels: ResMut<Levels>,
+    mut next_state: ResMut<NextState<GameState>>,
+    loading_screen: Query<Entity, With<OnLoadingScreen>>,
+) {
+    let still_loading = levels.handles.iter().any(|handle| {
+        !matches!(
+            asset_server.get_load_state(handle),
+            LoadState::Loaded | LoadState::Failed
+        )
+    });
+    if still_loading {
+        return;
+    }
+
+    // broken level files are left out, the AssetServer has already logged what is wrong with them
+    levels
+        .handles
+        .retain(|handle| asset_server.get_load_state(handle) == LoadState::Loaded);
+
+    if !levels.handles.is_empty() {
+        next_state.set(GameState::MainMenu);
+    } else if !loading_screen.is_empty() {
+        // without a single playable level the game can't start, replace "LOADING" with an explanation
+        for entity in &loading_screen {
+            commands.entity(entity).despawn_recursive();
+        }
+        error!("no playable level found in assets/levels");
+        spawn_menu_text(
+            &mut commands,
+            OnNoLevelsScreen,
+            "NO LEVELS",
+            "No playable level found in assets/levels",
+        );
+    }
+}
+
+// moves on to the next level, its bricks are spawned when the game enters Playing again
+fn advance_level(mut level: ResMut<CurrentLevel>, score: Res<Scoreboard>) {
+    level.index += 1;
+    level.starting_score = score.score;
+}
+
+// takes one health off every brick a ball bounced off, and removes the bricks that have none left.
+// Steel bricks don't lose health, explosive bricks take their neighbours with them when they break,
+// and regenerating bricks are only hidden until regenerate_bricks brings them back.
+fn damage_bricks(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut brick_hit_events: EventWriter<BrickHitEvent>,
+    // If an entity has a Brick component, the query provides mutable access to it, allowing you to modify the Brick (like changing its health).
+    mut brick_query: Query<(Entity, &mut Brick, &Transform)>,
+) {
+    // the bricks still to be hit: first the ones the balls bounced off, then the ones caught in explosions
+    let mut to_hit: Vec<Entity> = collision_events
+        .iter()
+        .filter(|collision| collision.surface == CollisionSurface::Brick)
+        .map(|collision| collision.other)
+        .collect();
+
+    while let Some(entity) = to_hit.pop() {
+        let Ok((_, mut brick, transform)) = brick_query.get_mut(entity) else {
+            continue;
+        };
+        // two balls (or a ball and an explosion) can hit the same brick in one tick, a broken brick can't be hit again
+        if brick.health <= 0 {
+            continue;
+        }
+        let position = transform.translation.truncate();
 
-                if let Some(mut brick) = opt_brick {
-                    score.score += 1;
-                    // The health of the brick is then decreased by 1. 
-                    // However, to avoid negative health values, the max(0) method ensures that the health doesn't drop below zero
-                    // This line effectively says, "Reduce the brick's health by one, but if it drops below zero, just set it to zero."
-                    brick.health = (brick.health - 1).max(0);
+        if brick.kind == BrickKind::Steel {
+            // steel can't be broken and isn't worth any points, the hit is only sent for its sound
+            brick_hit_events.send(BrickHitEvent {
+                kind: brick.kind,
+                destroyed: false,
+                points: 0,
+                position,
+            });
+            continue;
+        }
+
+        // The health of the brick is then decreased by 1. 
+        // However, to avoid negative health values, the max(0) method ensures that the health doesn't drop below zero
+        // This line effectively says, "Reduce the brick's health by one, but if it drops below zero, just set it to zero."
+        brick.health = (brick.health - 1).max(0);
+
+        // checks if the brick's health is now zero or less. If it is, the brick needs to be removed from the game.
+        let destroyed = brick.health <= 0;
+        let points = if destroyed {
+            brick.hit_score + brick.destroy_score
+        } else {
+            brick.hit_score
+        };
+        // kind is copied, so `brick` isn't borrowed anymore when the other bricks are looked at below
+        let kind = brick.kind;
 
+        if destroyed {
+            match kind {
+                BrickKind::Regenerating { delay } => {
+                    // without a Collider the ball flies through it, and it doesn't count for the win condition
+                    commands.entity(entity).remove::<Collider>().insert((
+                        Visibility::Hidden,
+                        RegenerationTimer(Timer::from_seconds(delay, TimerMode::Once)),
+                    ));
+                }
+                _ => commands.entity(entity).despawn(), // Despawn the Brick if health is 0 or less
+            }
 
-                    // checks if the brick's health is now zero or less. If it is, the brick needs to be removed from the game.
-                    if brick.health <= 0 {
-                        commands.entity(other_entity).despawn(); // Despawn the Brick if health is 0 or less
+            if let BrickKind::Explosive { radius } = kind {
+                for (other_entity, other_brick, other_transform) in &brick_query {
+                    let in_range =
+                        other_transform.translation.truncate().distance(position) <= radius;
+                    // steel bricks survive explosions
+                    if other_entity != entity
+                        && in_range
+                        && other_brick.health > 0
+                        && other_brick.kind != BrickKind::Steel
+                    {
+                        to_hit.push(other_entity);
                     }
                 }
+            }
+        }
+
+        brick_hit_events.send(BrickHitEvent {
+            kind,
+            destroyed,
+            points,
+            position,
+        });
+    }
+}
+
+// a broken "extra ball" brick releases a new ball where it was
+fn spawn_extra_balls(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
+    mut brick_hit_events: EventReader<BrickHitEvent>,
+    ball_speed: Res<BallSpeed>,
+    mut rng: ResMut<GameRng>,
+) {
+    for brick_hit in brick_hit_events.iter() {
+        if brick_hit.destroyed && brick_hit.kind == BrickKind::ExtraBall {
+            // picked before the call, rng can only be lent to one of them at a time
+            let velocity = ball_speed.current * random_ball_direction(&config, &mut *rng);
+            spawn_ball(
+                &mut commands,
+                &asset_server,
+                &config,
+                &mut *rng,
+                brick_hit.position.extend(config.ball_starting_position().z),
+                velocity,
+            );
+        }
+    }
+}
+
+// brings broken regenerating bricks back, at full health, once their timer has finished
+fn regenerate_bricks(
+    mut commands: Commands,
+    config: Res<GameConfig>,
+    time_step: Res<FixedTime>,
+    mut query: Query<(Entity, &mut Brick, &mut RegenerationTimer)>,
+) {
+    for (entity, mut brick, mut timer) in &mut query {
+        // tick() moves the timer forward by the time of one FixedUpdate step
+        if !timer.0.tick(time_step.period).finished() {
+            continue;
+        }
+        // changing the health also makes update_brick_colors give it back its full health color
+        brick.health = brick.max_health;
+        commands
+            .entity(entity)
+            .remove::<RegenerationTimer>()
+            .insert((
+                Collider {
+                    size: config.brick_size,
+                },
+                Visibility::Inherited,
+            ));
+    }
+}
+
+// adds the points of every brick hit to the score
+fn update_score(mut score: ResMut<Scoreboard>, mut brick_hit_events: EventReader<BrickHitEvent>) {
+    for brick_hit in brick_hit_events.iter() {
+        score.score += brick_hit.points;
+    }
+}
+
+// shows how much health a brick has left: the more it has been hit, the closer its color is to last_hit
+// Changed<Brick> only picks the bricks whose Brick component was modified (by damage_bricks) since the last run
+fn update_brick_colors(mut query: Query<(&Brick, &BrickColors, &mut Sprite), Changed<Brick>>) {
+    for (brick, colors, mut sprite) in &mut query {
+        // a brick with a single health point is never seen damaged
+        if brick.max_health <= 1 {
+            continue;
+        }
+        // 0.0 at full health, 1.0 once a single health point is left
+        let damage = (brick.max_health - brick.health) as f32 / (brick.max_health - 1) as f32;
+        sprite.color = mix_colors(colors.full_health, colors.last_hit, damage.min(1.0));
+    }
+}
+
+// The further from the middle of the paddle the ball lands, the more it is sent sideways:
+// the middle sends it straight up, the edges send it off at paddle_max_bounce_angle.
+// This lets the player aim. The speed of the ball doesn't change.
+fn paddle_bounce_velocity(
+    config: &GameConfig,
+    speed: f32,
+    ball_x: f32,
+    paddle_x: f32,
+    paddle_width: f32,
+) -> Vec2 {
+    // -1.0 at the left edge of the paddle, 0.0 in the middle, 1.0 at the right edge
+    // clamp keeps the value in that range when the ball only clips the corner of the paddle
+    let hit_offset = ((ball_x - paddle_x) / (paddle_width * 0.5)).clamp(-1.0, 1.0);
+    let angle = hit_offset * config.paddle_max_bounce_angle;
+    speed * tilted_up(angle)
+}
 
-                // play sound
-                // commands.spawn(AudioBundle {
-                //     source: collision_sound.clone(),
-                //     settings: PlaybackSettings::DESPAWN,
-                // });
+// a ball bouncing off a moving paddle gets spin from it, see curve_balls
+fn spin_balls_off_paddle(
+    config: Res<GameConfig>,
+    mut collision_events: EventReader<CollisionEvent>,
+    paddle_query: Query<&Velocity, With<Paddle>>,
+    mut spin_query: Query<&mut Spin>,
+) {
+    for collision in collision_events.iter() {
+        if collision.surface != CollisionSurface::Paddle {
+            continue;
+        }
+        let (Ok(paddle_velocity), Ok(mut spin)) =
+            (paddle_query.get(collision.other), spin_query.get_mut(collision.ball))
+        else {
+            continue;
+        };
+        // moving right (positive x) makes the ball turn right, which is a negative (clockwise) rate
+        spin.rate = (-paddle_velocity.x * config.ball_spin_per_paddle_speed)
+            .clamp(-config.ball_max_spin, config.ball_max_spin);
+    }
+}
+
+// turns the direction of every spinning ball a little each tick, so its path bends, and lets the spin wear off
+fn curve_balls(
+    config: Res<GameConfig>,
+    time_step: Res<FixedTime>,
+    mut ball_query: Query<(&mut Velocity, &mut Spin), FreeBall>,
+) {
+    let dt = time_step.period.as_secs_f32();
+    for (mut velocity, mut spin) in &mut ball_query {
+        if spin.rate == 0.0 {
+            continue;
+        }
+        // from_angle(a).rotate(v) turns v by the angle a, its length (the speed) doesn't change
+        velocity.0 = Vec2::from_angle(spin.rate * dt).rotate(velocity.0);
+        spin.rate *= (1.0 - config.ball_spin_decay * dt).max(0.0);
+    }
+}
+
+fn update_scoreboard(
+    score: Res<Scoreboard>,
+    lives: Res<Lives>,
+    level: Res<CurrentLevel>,
+    mut query: Query<&mut Text, With<ScoreboardText>>,
+) {
+    // a loop instead of single_mut(), because the scoreboard only exists while a round is on screen
+    for mut text in &mut query {
+        // updates the second section of the Text component with the current game score.
+        // The scoreboard text is split into sections, with the first section being static text like "Score: " and the second section (sections[1]) being the part that displays the actual numeric score.
+        text.sections[1].value = score.score.to_string();
+        // sections[3] comes after "  Lives: "
+        text.sections[3].value = lives.remaining.to_string();
+        // levels are counted from 1 on screen
+        text.sections[5].value = (level.index + 1).to_string();
+    }
+}
+
+// switches between states when the player presses a key
+fn change_state_on_input(
+    actions: Res<InputActions>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut app_exit_events: EventWriter<bevy::app::AppExit>,
+    mut saved_run: ResMut<SavedRun>,
+) {
+    match state.get() {
+        // nothing to do until the levels are loaded
+        GameState::Loading => {}
+        GameState::MainMenu => {
+            if actions.menu_confirm {
+                next_state.set(GameState::Playing);
+            } else if actions.continue_run && saved_run.exists() {
+                saved_run.continue_run();
+                next_state.set(GameState::Playing);
+            } else if actions.menu_back {
+                app_exit_events.send(bevy::app::AppExit);
+            }
+        }
+        GameState::Playing => {
+            if actions.pause_pressed {
+                next_state.set(GameState::Paused);
+            }
+        }
+        // the pause menu has its own options, see navigate_pause_menu
+        GameState::Paused => {}
+        // Enter confirms the name, see enter_high_score_name
+        GameState::NewHighScore => {}
+        GameState::LevelCleared => {
+            if actions.menu_confirm {
+                next_state.set(GameState::Playing);
+            }
+        }
+        GameState::GameOver => {
+            // Playing builds a brand new round, because the old one is despawned when GameOver is left
+            if actions.menu_confirm {
+                next_state.set(GameState::Playing);
+            } else if actions.main_menu {
+                next_state.set(GameState::MainMenu);
             }
         }
     }
 }
 
-fn update_scoreboard(score: Res<Scoreboard>, mut query: Query<&mut Text>) {
-    let mut text = query.single_mut();
-    // updates the second section of the Text component with the current game score.
-    // The scoreboard text is assumed to be split into sections, with the first section likely being static text like "Score: " and the second section (sections[1]) being the part that displays the actual numeric score.
-    text.sections[1].value = score.score.to_string();
-}
\ No newline at end of file
+// a new run starts on the first level with no points and all lives
+fn reset_run(
+    config: Res<GameConfig>,
+    mut score: ResMut<Scoreboard>,
+    mut lives: ResMut<Lives>,
+    mut level: ResMut<CurrentLevel>,
+) {
+    score.score = 0;
+    lives.remaining = config.starting_lives;
+    level.index = 0;
+    level.starting_score = 0;
+}
+
+// removes every entity with the marker component T (and their children, e.g. the text inside a menu)
+fn despawn_screen<T: Component>(mut commands: Commands, query: Query<Entity, With<T>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// spawns a centered block of text for a menu screen, tagged with the screen's marker component.
+// Returns the text entity, for screens that add to their text later (the pause menu and the name entry)
+fn spawn_menu_text(
+    commands: &mut Commands,
+    marker: impl Component,
+    title: &str,
+    hint: &str,
+) -> Entity {
+    let text = commands
+        .spawn(
+            TextBundle::from_sections([
+                TextSection::new(
+                    format!("{title}\n"),
+                    TextStyle {
+                        font_size: MENU_TITLE_FONT_SIZE,
+                        color: MENU_COLOR,
+                        ..default()
+                    },
+                ),
+                TextSection::new(
+                    hint,
+                    TextStyle {
+                        font_size: MENU_HINT_FONT_SIZE,
+                        color: TEXT_COLOR,
+                        ..default()
+                    },
+                ),
+            ])
+            .with_text_alignment(TextAlignment::Center),
+        )
+        .id();
+    commands
+        // an invisible node covering the whole window, used to center the text
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            marker,
+        ))
+        .add_child(text);
+    text
+}
+
+// the hints name the keys from config/input.ron, see controls.rs
+fn show_main_menu(
+    mut commands: Commands,
+    saved_run: Res<SavedRun>,
+    bindings: Res<InputBindings>,
+) {
+    let continue_hint = match saved_run.level_number() {
+        Some(level) => format!(
+            "Press {} to continue your saved run (level {level})\n",
+            bindings.names(Binding::ContinueRun)
+        ),
+        None => String::new(),
+    };
+    spawn_menu_text(
+        &mut commands,
+        OnMainMenuScreen,
+        "BREAKOUT",
+        &format!(
+            "{continue_hint}Press {} to start a new run, {} to quit\n\
+         Move the paddle left with {} and right with {}, or with the mouse or a gamepad, pause with {}\n\
+         {} serves the ball (it goes by itself after 3 seconds) and fires the laser\n\
+         Sound: {} to mute, {} and {} for volume, {} and {} for effects volume",
+            bindings.names(Binding::MenuConfirm),
+            bindings.names(Binding::MenuBack),
+            bindings.names(Binding::MoveLeft),
+            bindings.names(Binding::MoveRight),
+            bindings.names(Binding::Pause),
+            bindings.names(Binding::Launch),
+            bindings.names(Binding::ToggleMute),
+            bindings.names(Binding::MasterVolumeDown),
+            bindings.names(Binding::MasterVolumeUp),
+            bindings.names(Binding::SfxVolumeDown),
+            bindings.names(Binding::SfxVolumeUp),
+        ),
+    );
+}
+
+fn show_level_cleared(
+    mut commands: Commands,
+    current_level: Res<CurrentLevel>,
+    levels: Res<Levels>,
+    level_assets: Res<Assets<Level>>,
+    bindings: Res<InputBindings>,
+) {
+    let name = levels
+        .get(current_level.index, &level_assets)
+        .map_or_else(|| format!("Level {}", current_level.index + 1), |level| level.name.clone());
+    spawn_menu_text(
+        &mut commands,
+        OnLevelClearedScreen,
+        &format!("{} CLEARED", name.to_uppercase()),
+        &format!(
+            "Press {} to start the next level",
+            bindings.names(Binding::MenuConfirm)
+        ),
+    );
+}
+
+// shows the final score in the middle of the screen once the run is over
+fn show_game_over(
+    mut commands: Commands,
+    score: Res<Scoreboard>,
+    lives: Res<Lives>,
+    high_scores: Res<HighScores>,
+    bindings: Res<InputBindings>,
+) {
+    // lives are only left when the run ended by clearing the last level
+    let title = if lives.remaining > 0 {
+        "ALL LEVELS CLEARED"
+    } else {
+        "GAME OVER"
+    };
+    spawn_menu_text(
+        &mut commands,
+        OnGameOverScreen,
+        &format!("{title}\nFinal score: {}", score.score),
+        &format!(
+            "HIGH SCORES\n{}\nPress {} to play again\nPress {} to go back to the main menu",
+            high_scores.table_text(),
+            bindings.names(Binding::MenuConfirm),
+            bindings.names(Binding::MainMenu)
+        ),
+    );
+}
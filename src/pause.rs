@@ -0,0 +1,208 @@
+use bevy::{app::AppExit, prelude::*};
+
+use crate::{
+    audio::AudioSettings, controls::InputActions, spawn_menu_text, CurrentLevel, GameState,
+    OnGameScreen, OnPauseScreen, Scoreboard, MENU_COLOR, MENU_HINT_FONT_SIZE, MENU_TITLE_FONT_SIZE,
+    SCORE_COLOR, TEXT_COLOR,
+};
+
+// ** Pause menu **
+// Pausing (P, Escape or Start) shows this menu over the frozen round. Up and down pick an option, Enter (or the
+// South button) chooses it. The settings page changes the sound with left and right, and back returns to the menu.
+
+// the options of each page, in the order they are shown
+const MAIN_OPTIONS: [PauseOption; 5] = [
+    PauseOption::Resume,
+    PauseOption::RestartLevel,
+    PauseOption::Settings,
+    PauseOption::MainMenu,
+    PauseOption::Quit,
+];
+const SETTINGS_OPTIONS: [PauseOption; 4] = [
+    PauseOption::MasterVolume,
+    PauseOption::EffectsVolume,
+    PauseOption::Mute,
+    PauseOption::Back,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum PauseOption {
+    Resume,
+    RestartLevel,
+    Settings,
+    MainMenu,
+    Quit,
+    MasterVolume,
+    EffectsVolume,
+    Mute,
+    Back,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+enum PausePage {
+    #[default]
+    Main,
+    Settings,
+}
+
+// which page of the pause menu is shown, and which of its options is selected
+#[derive(Resource, Default)]
+pub struct PauseMenu {
+    page: PausePage,
+    selected: usize,
+}
+
+impl PauseMenu {
+    fn options(&self) -> &'static [PauseOption] {
+        match self.page {
+            PausePage::Main => &MAIN_OPTIONS,
+            PausePage::Settings => &SETTINGS_OPTIONS,
+        }
+    }
+
+    fn open(&mut self, page: PausePage) {
+        self.page = page;
+        self.selected = 0;
+    }
+}
+
+// marks the text of the pause menu, which update_pause_menu_text rewrites as the selection changes
+#[derive(Component)]
+pub(crate) struct PauseMenuText;
+
+// opens the pause menu on its first page, with Resume selected
+pub fn show_pause_menu(mut commands: Commands, mut menu: ResMut<PauseMenu>) {
+    menu.open(PausePage::Main);
+    // update_pause_menu_text writes the title and the options
+    let text = spawn_menu_text(&mut commands, OnPauseScreen, "", "");
+    commands.entity(text).insert(PauseMenuText);
+}
+
+// moves through the pause menu and does what the chosen option says
+#[allow(clippy::too_many_arguments)]
+pub fn navigate_pause_menu(
+    mut commands: Commands,
+    actions: Res<InputActions>,
+    mut menu: ResMut<PauseMenu>,
+    mut audio_settings: ResMut<AudioSettings>,
+    mut score: ResMut<Scoreboard>,
+    current_level: Res<CurrentLevel>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut app_exit_events: EventWriter<AppExit>,
+    game_screen_query: Query<Entity, With<OnGameScreen>>,
+) {
+    // pausing again (or going back) resumes the game, or returns from the settings to the first page
+    if actions.pause_pressed || actions.menu_back {
+        match menu.page {
+            PausePage::Main => next_state.set(GameState::Playing),
+            PausePage::Settings => menu.open(PausePage::Main),
+        }
+        return;
+    }
+
+    let count = menu.options().len();
+    // going up from the first option wraps around to the last one, and down from the last to the first
+    if actions.menu_up {
+        menu.selected = (menu.selected + count - 1) % count;
+    }
+    if actions.menu_down {
+        menu.selected = (menu.selected + 1) % count;
+    }
+
+    let option = menu.options()[menu.selected];
+    let direction = match (actions.menu_left, actions.menu_right) {
+        (true, false) => -1.0,
+        (false, true) => 1.0,
+        _ => 0.0,
+    };
+    if direction != 0.0 {
+        match option {
+            PauseOption::MasterVolume => audio_settings.step_master_volume(direction),
+            PauseOption::EffectsVolume => audio_settings.step_sfx_volume(direction),
+            PauseOption::Mute => audio_settings.muted = !audio_settings.muted,
+            _ => {}
+        }
+    }
+
+    if !actions.menu_confirm {
+        return;
+    }
+    match option {
+        PauseOption::Resume => next_state.set(GameState::Playing),
+        PauseOption::RestartLevel => {
+            // without a paddle, entering Playing builds the level again from its file
+            for entity in &game_screen_query {
+                commands.entity(entity).despawn_recursive();
+            }
+            // the points scored in this attempt are taken back, lives are not given back
+            score.score = current_level.starting_score;
+            next_state.set(GameState::Playing);
+        }
+        PauseOption::Settings => menu.open(PausePage::Settings),
+        PauseOption::MainMenu => next_state.set(GameState::MainMenu),
+        PauseOption::Quit => app_exit_events.send(AppExit),
+        PauseOption::Mute => audio_settings.muted = !audio_settings.muted,
+        PauseOption::Back => menu.open(PausePage::Main),
+        // the volumes are changed with left and right
+        PauseOption::MasterVolume | PauseOption::EffectsVolume => {}
+    }
+}
+
+// writes the options of the current page, with the selected one highlighted
+pub fn update_pause_menu_text(
+    menu: Res<PauseMenu>,
+    audio_settings: Res<AudioSettings>,
+    mut query: Query<&mut Text, With<PauseMenuText>>,
+) {
+    // only rewritten when something shown changed (show_pause_menu changes the menu when the text is spawned)
+    if !menu.is_changed() && !audio_settings.is_changed() {
+        return;
+    }
+    let title = match menu.page {
+        PausePage::Main => "PAUSED",
+        PausePage::Settings => "SETTINGS",
+    };
+    let mut sections = vec![TextSection::new(
+        format!("{title}\n"),
+        TextStyle {
+            font_size: MENU_TITLE_FONT_SIZE,
+            color: MENU_COLOR,
+            ..default()
+        },
+    )];
+
+    for (index, option) in menu.options().iter().enumerate() {
+        let label = match option {
+            PauseOption::Resume => "Resume".to_string(),
+            PauseOption::RestartLevel => "Restart level".to_string(),
+            PauseOption::Settings => "Settings".to_string(),
+            PauseOption::MainMenu => "Main menu".to_string(),
+            PauseOption::Quit => "Quit".to_string(),
+            // volumes are shown in percent, rounded to a whole number
+            PauseOption::MasterVolume => {
+                format!("< Volume: {:.0}% >", audio_settings.master_volume * 100.0)
+            }
+            PauseOption::EffectsVolume => {
+                format!("< Effects: {:.0}% >", audio_settings.sfx_volume * 100.0)
+            }
+            PauseOption::Mute => {
+                let sound = if audio_settings.muted { "off" } else { "on" };
+                format!("< Sound: {sound} >")
+            }
+            PauseOption::Back => "Back".to_string(),
+        };
+        let selected = index == menu.selected;
+        sections.push(TextSection::new(
+            format!("{}{label}\n", if selected { "> " } else { "" }),
+            TextStyle {
+                font_size: MENU_HINT_FONT_SIZE,
+                color: if selected { SCORE_COLOR } else { TEXT_COLOR },
+                ..default()
+            },
+        ));
+    }
+
+    for mut text in &mut query {
+        text.sections = sections.clone();
+    }
+}
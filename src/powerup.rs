@@ -0,0 +1,355 @@
+use bevy::{prelude::*, utils::HashMap};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::GameConfig,
+    controls::InputActions,
+    rng::GameRng,
+    serve::{FreeBall, StuckToPaddle},
+    spawn_ball, Brick, BrickHitEvent, Collider, CollisionEvent, CollisionSurface, OnGameScreen,
+    Paddle, Velocity,
+};
+
+// ** Power-ups **
+// A broken brick sometimes drops a capsule. It falls (moved by apply_velocity like anything else with a Velocity)
+// and gives its power-up when it touches the paddle. Multi-ball happens at once, the others last POWER_UP_DURATION
+// seconds: catching the same kind again starts its timer over, and different kinds are active at the same time.
+
+// chance (between 0.0 and 1.0) that a broken brick drops a capsule
+const POWER_UP_DROP_CHANCE: f64 = 0.15;
+const CAPSULE_SIZE: Vec2 = Vec2::new(40.0, 16.0);
+const CAPSULE_FALL_SPEED: f32 = 150.0;
+// how long a timed power-up lasts, in seconds
+const POWER_UP_DURATION: f32 = 10.0;
+
+// the paddle is this many times wider with WidePaddle
+pub const WIDE_PADDLE_SCALE: f32 = 1.5;
+// the balls move this many times slower with SlowBall
+const SLOW_BALL_SCALE: f32 = 0.6;
+// MultiBall adds two balls, sent off this far to the left and right of an existing ball
+// PI / 8.0 radians = 22.5 degrees
+const MULTI_BALL_SPREAD_ANGLE: f32 = std::f32::consts::PI / 8.0;
+
+// with Laser, holding space fires two bolts (one from each end of the paddle) every LASER_COOLDOWN seconds
+const LASER_COOLDOWN: f32 = 0.3;
+const LASER_BOLT_SIZE: Vec2 = Vec2::new(4.0, 16.0);
+const LASER_BOLT_SPEED: f32 = 700.0;
+const LASER_BOLT_COLOR: Color = Color::rgb(1.0, 0.2, 0.2);
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum PowerUpKind {
+    WidePaddle,
+    SlowBall,
+    MultiBall,
+    // a ball landing on the paddle stays on it until space is pressed, see serve.rs
+    StickyPaddle,
+    Laser,
+}
+
+impl PowerUpKind {
+    const ALL: [PowerUpKind; 5] = [
+        PowerUpKind::WidePaddle,
+        PowerUpKind::SlowBall,
+        PowerUpKind::MultiBall,
+        PowerUpKind::StickyPaddle,
+        PowerUpKind::Laser,
+    ];
+
+    // the capsule color tells the player which power-up is falling
+    fn color(self) -> Color {
+        match self {
+            PowerUpKind::WidePaddle => Color::rgb(0.2, 0.4, 0.9),
+            PowerUpKind::SlowBall => Color::rgb(0.2, 0.7, 0.3),
+            PowerUpKind::MultiBall => Color::rgb(0.9, 0.7, 0.1),
+            PowerUpKind::StickyPaddle => Color::rgb(0.7, 0.3, 0.8),
+            PowerUpKind::Laser => LASER_BOLT_COLOR,
+        }
+    }
+}
+
+// a falling power-up capsule
+#[derive(Component)]
+pub struct PowerUpCapsule {
+    kind: PowerUpKind,
+}
+
+impl PowerUpCapsule {
+    pub fn kind(&self) -> PowerUpKind {
+        self.kind
+    }
+}
+
+#[derive(Component)]
+pub struct LaserBolt;
+
+// the timed power-ups that are active, with the time they have left
+#[derive(Resource, Default)]
+pub struct ActivePowerUps {
+    timers: HashMap<PowerUpKind, Timer>,
+}
+
+impl ActivePowerUps {
+    pub fn is_active(&self, kind: PowerUpKind) -> bool {
+        self.timers.contains_key(&kind)
+    }
+
+    // ends every power-up at once, e.g. when a new round starts
+    pub fn clear(&mut self) {
+        self.timers.clear();
+    }
+
+    // the active power-ups with the seconds they have left, used to save the run
+    pub fn remaining(&self) -> Vec<(PowerUpKind, f32)> {
+        self.timers
+            .iter()
+            .map(|(kind, timer)| (*kind, timer.remaining_secs()))
+            .collect()
+    }
+
+    // makes `kind` active for `seconds` more, used to continue a saved run
+    pub fn set_remaining(&mut self, kind: PowerUpKind, seconds: f32) {
+        self.timers
+            .insert(kind, Timer::from_seconds(seconds, TimerMode::Once));
+    }
+
+    // how fast the balls move compared to their velocity: 1.0 normally, less with SlowBall
+    pub fn ball_speed_scale(&self) -> f32 {
+        if self.is_active(PowerUpKind::SlowBall) {
+            SLOW_BALL_SCALE
+        } else {
+            1.0
+        }
+    }
+}
+
+// a broken brick has a POWER_UP_DROP_CHANCE chance to drop a random capsule
+pub fn drop_power_ups(
+    mut commands: Commands,
+    mut brick_hit_events: EventReader<BrickHitEvent>,
+    mut rng: ResMut<GameRng>,
+) {
+    for brick_hit in brick_hit_events.iter() {
+        if !brick_hit.destroyed || !rng.gen_bool(POWER_UP_DROP_CHANCE) {
+            continue;
+        }
+        // choose() picks a random element of the array, it is only None for an empty array
+        let kind = *PowerUpKind::ALL.choose(&mut *rng).unwrap();
+        spawn_capsule(&mut commands, brick_hit.position, kind);
+    }
+}
+
+// a capsule falling from `position`, also used to continue a saved run
+pub fn spawn_capsule(commands: &mut Commands, position: Vec2, kind: PowerUpKind) {
+    commands.spawn((
+        SpriteBundle {
+            transform: Transform::from_translation(position.extend(0.5)),
+            sprite: Sprite {
+                color: kind.color(),
+                custom_size: Some(CAPSULE_SIZE),
+                ..default()
+            },
+            ..default()
+        },
+        PowerUpCapsule { kind },
+        Velocity(Vec2::new(0.0, -CAPSULE_FALL_SPEED)),
+        OnGameScreen,
+    ));
+}
+
+// gives the power-up of every capsule touching the paddle, and removes the capsules that fell past it
+#[allow(clippy::too_many_arguments)]
+pub fn collect_power_ups(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
+    mut power_ups: ResMut<ActivePowerUps>,
+    mut rng: ResMut<GameRng>,
+    capsule_query: Query<(Entity, &Transform, &PowerUpCapsule)>,
+    paddle_query: Query<(&Transform, &Collider), With<Paddle>>,
+    ball_query: Query<(&Transform, &Velocity), FreeBall>,
+) {
+    let Ok((paddle_transform, paddle_collider)) = paddle_query.get_single() else {
+        return;
+    };
+
+    for (capsule_entity, capsule_transform, capsule) in &capsule_query {
+        let offset = (capsule_transform.translation - paddle_transform.translation).truncate();
+        // two boxes overlap when their centers are closer than half their sizes added together, on both axes
+        let touching = offset
+            .abs()
+            .cmple((CAPSULE_SIZE + paddle_collider.size) * 0.5)
+            .all();
+
+        if touching {
+            commands.entity(capsule_entity).despawn();
+            if capsule.kind == PowerUpKind::MultiBall {
+                // the new balls leave from a ball in play, so they don't appear out of nowhere
+                if let Some((ball_transform, ball_velocity)) = ball_query.iter().next() {
+                    for angle in [-MULTI_BALL_SPREAD_ANGLE, MULTI_BALL_SPREAD_ANGLE] {
+                        spawn_ball(
+                            &mut commands,
+                            &asset_server,
+                            &config,
+                            &mut *rng,
+                            ball_transform.translation,
+                            Vec2::from_angle(angle).rotate(ball_velocity.0),
+                        );
+                    }
+                }
+            } else {
+                // inserting replaces the timer of a power-up that is already active, so it starts over
+                power_ups.timers.insert(
+                    capsule.kind,
+                    Timer::from_seconds(POWER_UP_DURATION, TimerMode::Once),
+                );
+            }
+        } else if capsule_transform.translation.y < config.bottom_wall {
+            commands.entity(capsule_entity).despawn();
+        }
+    }
+}
+
+// counts down the timed power-ups and ends the ones whose time is up
+pub fn tick_power_ups(time_step: Res<FixedTime>, mut power_ups: ResMut<ActivePowerUps>) {
+    for timer in power_ups.timers.values_mut() {
+        timer.tick(time_step.period);
+    }
+    // retain keeps only the entries for which the closure returns true
+    power_ups.timers.retain(|_, timer| !timer.finished());
+}
+
+// makes the paddle wider while WidePaddle is active, and gives it back its normal size afterwards
+pub fn resize_paddle(
+    config: Res<GameConfig>,
+    power_ups: Res<ActivePowerUps>,
+    mut paddle_query: Query<(&mut Collider, &mut Sprite), With<Paddle>>,
+) {
+    let width = if power_ups.is_active(PowerUpKind::WidePaddle) {
+        config.paddle_size.x * WIDE_PADDLE_SCALE
+    } else {
+        config.paddle_size.x
+    };
+    for (mut collider, mut sprite) in &mut paddle_query {
+        // only written when it changes, so Bevy doesn't see a change every tick
+        if collider.size.x != width {
+            collider.size.x = width;
+            sprite.custom_size = Some(collider.size);
+        }
+    }
+}
+
+// while StickyPaddle is active, a ball landing on top of the paddle stays there
+pub fn stick_balls_to_paddle(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    power_ups: Res<ActivePowerUps>,
+    transform_query: Query<&Transform>,
+) {
+    if !power_ups.is_active(PowerUpKind::StickyPaddle) {
+        collision_events.clear();
+        return;
+    }
+    for collision in collision_events.iter() {
+        if collision.surface != CollisionSurface::Paddle || collision.normal != Vec2::Y {
+            continue;
+        }
+        let Ok(paddle_transform) = transform_query.get(collision.other) else {
+            continue;
+        };
+        commands
+            .entity(collision.ball)
+            .insert(StuckToPaddle::caught(
+                collision.position.x - paddle_transform.translation.x,
+            ));
+    }
+}
+
+// while Laser is active, holding the launch action (space by default) fires bolts from both ends of the paddle
+pub fn fire_lasers(
+    mut commands: Commands,
+    actions: Res<InputActions>,
+    time_step: Res<FixedTime>,
+    power_ups: Res<ActivePowerUps>,
+    // Local is a value kept between runs of this system: the seconds left before the next shot
+    mut cooldown: Local<f32>,
+    paddle_query: Query<(&Transform, &Collider), With<Paddle>>,
+) {
+    *cooldown = (*cooldown - time_step.period.as_secs_f32()).max(0.0);
+    if !power_ups.is_active(PowerUpKind::Laser) || !actions.launch_held || *cooldown > 0.0 {
+        return;
+    }
+    let Ok((paddle_transform, paddle_collider)) = paddle_query.get_single() else {
+        return;
+    };
+    *cooldown = LASER_COOLDOWN;
+
+    let paddle_half_size = paddle_collider.size * 0.5;
+    for side in [-1.0, 1.0] {
+        // just above the paddle, so the bolt doesn't start out touching it
+        let position = paddle_transform.translation.truncate()
+            + Vec2::new(
+                side * (paddle_half_size.x - LASER_BOLT_SIZE.x),
+                paddle_half_size.y + LASER_BOLT_SIZE.y * 0.5,
+            );
+        spawn_laser_bolt(&mut commands, position);
+    }
+}
+
+// a bolt flying up from `position`, also used to continue a saved run
+pub fn spawn_laser_bolt(commands: &mut Commands, position: Vec2) {
+    commands.spawn((
+        SpriteBundle {
+            transform: Transform::from_translation(position.extend(0.5)),
+            sprite: Sprite {
+                color: LASER_BOLT_COLOR,
+                custom_size: Some(LASER_BOLT_SIZE),
+                ..default()
+            },
+            ..default()
+        },
+        LaserBolt,
+        Velocity(Vec2::new(0.0, LASER_BOLT_SPEED)),
+        OnGameScreen,
+    ));
+}
+
+// a bolt stops at the first wall or brick it touches. Hitting a brick sends the same CollisionEvent a ball does,
+// so damage_bricks (and everything else reacting to brick hits) handles it like any other hit
+pub fn check_laser_hits(
+    mut commands: Commands,
+    config: Res<GameConfig>,
+    mut collision_events: EventWriter<CollisionEvent>,
+    bolt_query: Query<(Entity, &Transform), With<LaserBolt>>,
+    collider_query: Query<(Entity, &Transform, &Collider, Option<&Brick>), Without<Paddle>>,
+) {
+    for (bolt_entity, bolt_transform) in &bolt_query {
+        let bolt_position = bolt_transform.translation.truncate();
+
+        let hit = collider_query.iter().find(|(_, transform, collider, _)| {
+            let offset = bolt_position - transform.translation.truncate();
+            offset
+                .abs()
+                .cmple((LASER_BOLT_SIZE + collider.size) * 0.5)
+                .all()
+        });
+
+        match hit {
+            Some((other_entity, _, _, opt_brick)) => {
+                if opt_brick.is_some() {
+                    collision_events.send(CollisionEvent {
+                        ball: bolt_entity,
+                        other: other_entity,
+                        surface: CollisionSurface::Brick,
+                        normal: Vec2::NEG_Y,
+                        position: bolt_position,
+                    });
+                }
+                commands.entity(bolt_entity).despawn();
+            }
+            // a bolt can't really get past the top wall, this is just in case
+            None if bolt_position.y > config.top_wall => commands.entity(bolt_entity).despawn(),
+            None => {}
+        }
+    }
+}
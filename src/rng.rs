@@ -0,0 +1,72 @@
+use bevy::prelude::*;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+
+// ** Random numbers **
+// Every random choice in the game (launch angles, ball colors, power-up drops) comes from GameRng, a single generator
+// started from a seed. The same seed with the same inputs plays the same game, so a bug can be played again.
+// The seed comes from --seed on the command line, or else from `seed` in config/game.ron, or else it is picked at
+// random. It is logged when the game starts.
+// In the windowed game the inputs also have to arrive on the same FixedUpdate steps, which is only certain in headless
+// mode (see headless.rs), where time moves by exactly one step every update.
+// The generator is ChaCha12Rng, the one behind rand's StdRng, because it can be saved: a continued run (see savegame.rs)
+// goes on with the same random numbers it would have had.
+
+#[derive(Resource, Clone, Serialize, Deserialize)]
+pub struct GameRng {
+    seed: u64,
+    rng: ChaCha12Rng,
+}
+
+impl GameRng {
+    // a generator started from `seed`, or from a random seed when there is none
+    pub fn new(seed: Option<u64>) -> Self {
+        // rand::random uses the operating system's random source, it is only used to pick the seed
+        let seed = seed.unwrap_or_else(rand::random);
+        GameRng {
+            seed,
+            rng: ChaCha12Rng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+// lets GameRng be used like any other generator: rng.gen_range(..), rng.gen_bool(..), slice.choose(&mut *rng), ...
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_generator_goes_on_with_the_same_numbers() {
+        let mut rng = GameRng::new(Some(7));
+        rng.next_u64();
+        let saved = ron::to_string(&rng).unwrap();
+        let mut loaded: GameRng = ron::from_str(&saved).unwrap();
+        assert_eq!(loaded.seed(), 7);
+        for _ in 0..10 {
+            assert_eq!(loaded.next_u64(), rng.next_u64());
+        }
+    }
+}
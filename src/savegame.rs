@@ -0,0 +1,324 @@
+use bevy::{app::AppExit, prelude::*, window::WindowCloseRequested};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ball_speed::{BallSpeed, SavedBallSpeed},
+    config::GameConfig,
+    data_file::{data_file_path, read_data_file, write_data_file, DataFileError, VersionedFile},
+    level::BrickKind,
+    powerup::{
+        spawn_capsule, spawn_laser_bolt, ActivePowerUps, LaserBolt, PowerUpCapsule, PowerUpKind,
+    },
+    rng::GameRng,
+    serve::StuckToPaddle,
+    spawn_ball, spawn_brick, Ball, Brick, BrickColors, Collider, CurrentLevel, Lives, Paddle,
+    RegenerationTimer, Scoreboard, Spin, Velocity,
+};
+
+// ** Saved run **
+// Quitting in the middle of a run (Quit in the pause menu, or closing the window while playing or paused) writes the
+// round to saved_run.ron, next to the high scores. The next time the game starts, the main menu offers to continue it:
+// the level, score, lives, bricks, balls, paddle, falling capsules, laser bolts, active power-ups, ball speed and the
+// random number generator come back exactly as they were. Only the laser's cooldown (at most 0.3 seconds) starts over.
+// A continued run is removed from the disk, so it can't be continued twice.
+
+const SAVE_FILE_NAME: &str = "saved_run.ron";
+
+// everything needed to build the round again. Positions and velocities are [x, y] (and z) arrays
+#[derive(Serialize, Deserialize)]
+pub struct SaveGame {
+    version: u32,
+    level_index: usize,
+    level_starting_score: usize,
+    score: usize,
+    lives: usize,
+    paddle_position: [f32; 3],
+    paddle_velocity: [f32; 2],
+    balls: Vec<SavedBall>,
+    bricks: Vec<SavedBrick>,
+    // the falling capsules, with their [x, y] positions
+    capsules: Vec<([f32; 2], PowerUpKind)>,
+    laser_bolts: Vec<[f32; 2]>,
+    // the timed power-ups with the seconds they had left
+    power_ups: Vec<(PowerUpKind, f32)>,
+    ball_speed: SavedBallSpeed,
+    // the generator with everything it has already given out, so the run goes on with the same random numbers
+    rng: GameRng,
+}
+
+impl VersionedFile for SaveGame {
+    // bump this when SaveGame changes. 2 added the starting speed of the life to ball_speed,
+    // 3 added the capsules, laser bolts and rng
+    const VERSION: u32 = 3;
+
+    fn version(&self) -> u32 {
+        self.version
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedBall {
+    position: [f32; 3],
+    velocity: [f32; 2],
+    spin: f32,
+    // Some for a ball sitting on the paddle, see StuckToPaddle
+    stuck: Option<SavedStuck>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedStuck {
+    offset: f32,
+    serve: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedBrick {
+    position: [f32; 2],
+    health: i8,
+    max_health: i8,
+    hit_score: usize,
+    destroy_score: usize,
+    kind: BrickKind,
+    // colors as [red, green, blue, alpha]
+    full_health_color: [f32; 4],
+    last_hit_color: [f32; 4],
+    // Some(seconds left) for a broken regenerating brick waiting to come back
+    regenerating: Option<f32>,
+}
+
+// the run found on the disk when the game started, if there is one
+#[derive(Resource, Default)]
+pub struct SavedRun {
+    game: Option<SaveGame>,
+    // set when the player picks Continue, restore_saved_run builds the round from `game` instead of the level file
+    continuing: bool,
+}
+
+impl SavedRun {
+    pub fn exists(&self) -> bool {
+        self.game.is_some()
+    }
+
+    // the level the saved run is on, counting from 1
+    pub fn level_number(&self) -> Option<usize> {
+        self.game.as_ref().map(|game| game.level_index + 1)
+    }
+
+    // asks for the saved run to be restored when the game enters Playing
+    pub fn continue_run(&mut self) {
+        self.continuing = self.game.is_some();
+    }
+}
+
+// run condition for restore_saved_run
+pub fn continuing_saved_run(saved_run: Res<SavedRun>) -> bool {
+    saved_run.continuing
+}
+
+// looks for a saved run when the game starts. A broken file is reported and ignored, the game starts without it
+pub fn load_saved_run(mut commands: Commands) {
+    let game = match data_file_path(SAVE_FILE_NAME).map(|path| read_data_file::<SaveGame>(&path)) {
+        Some(Ok(game)) => Some(game),
+        Some(Err(DataFileError::Read(err))) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Some(Err(err)) => {
+            warn!("invalid saved run {SAVE_FILE_NAME}: {err}, it can't be continued");
+            None
+        }
+        None => None,
+    };
+    commands.insert_resource(SavedRun {
+        game,
+        continuing: false,
+    });
+}
+
+// writes the round to the disk when the game is closed during Playing or Paused.
+// Quit in the pause menu sends AppExit, closing the window sends WindowCloseRequested
+#[allow(clippy::too_many_arguments)]
+pub fn save_run_on_exit(
+    mut app_exit_events: EventReader<AppExit>,
+    mut close_requested_events: EventReader<WindowCloseRequested>,
+    score: Res<Scoreboard>,
+    lives: Res<Lives>,
+    current_level: Res<CurrentLevel>,
+    power_ups: Res<ActivePowerUps>,
+    ball_speed: Res<BallSpeed>,
+    rng: Res<GameRng>,
+    paddle_query: Query<(&Transform, &Velocity), With<Paddle>>,
+    ball_query: Query<(&Transform, &Velocity, &Spin, Option<&StuckToPaddle>), With<Ball>>,
+    brick_query: Query<(&Transform, &Brick, &BrickColors, Option<&RegenerationTimer>)>,
+    capsule_query: Query<(&Transform, &PowerUpCapsule)>,
+    bolt_query: Query<&Transform, With<LaserBolt>>,
+) {
+    // iter().count() also marks the events as read
+    let exiting = app_exit_events.iter().count() + close_requested_events.iter().count() > 0;
+    if !exiting {
+        return;
+    }
+    let Ok((paddle_transform, paddle_velocity)) = paddle_query.get_single() else {
+        return;
+    };
+
+    let game = SaveGame {
+        version: SaveGame::VERSION,
+        level_index: current_level.index,
+        level_starting_score: current_level.starting_score,
+        score: score.score,
+        lives: lives.remaining,
+        paddle_position: paddle_transform.translation.to_array(),
+        paddle_velocity: paddle_velocity.to_array(),
+        balls: ball_query
+            .iter()
+            .map(|(transform, velocity, spin, stuck)| SavedBall {
+                position: transform.translation.to_array(),
+                velocity: velocity.to_array(),
+                spin: spin.rate,
+                stuck: stuck.map(|stuck| SavedStuck {
+                    offset: stuck.offset(),
+                    serve: stuck.is_serve(),
+                }),
+            })
+            .collect(),
+        bricks: brick_query
+            .iter()
+            .map(|(transform, brick, colors, regeneration)| SavedBrick {
+                position: transform.translation.truncate().to_array(),
+                health: brick.health,
+                max_health: brick.max_health,
+                hit_score: brick.hit_score,
+                destroy_score: brick.destroy_score,
+                kind: brick.kind,
+                full_health_color: colors.full_health.as_rgba_f32(),
+                last_hit_color: colors.last_hit.as_rgba_f32(),
+                regenerating: regeneration.map(|timer| timer.0.remaining_secs()),
+            })
+            .collect(),
+        capsules: capsule_query
+            .iter()
+            .map(|(transform, capsule)| {
+                (transform.translation.truncate().to_array(), capsule.kind())
+            })
+            .collect(),
+        laser_bolts: bolt_query
+            .iter()
+            .map(|transform| transform.translation.truncate().to_array())
+            .collect(),
+        power_ups: power_ups.remaining(),
+        ball_speed: SavedBallSpeed::from(&*ball_speed),
+        rng: rng.clone(),
+    };
+
+    let Some(path) = data_file_path(SAVE_FILE_NAME) else {
+        return;
+    };
+    match write_data_file(&path, &game) {
+        Ok(()) => info!("run saved, it can be continued from the main menu"),
+        Err(err) => warn!("could not save the run: {err}"),
+    }
+}
+
+// swaps the round spawn_playfield just built for the saved one. The walls and scoreboard are kept,
+// the bricks and balls are replaced and everything else is set back to how it was saved
+#[allow(clippy::too_many_arguments)]
+pub fn restore_saved_run(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
+    mut saved_run: ResMut<SavedRun>,
+    mut score: ResMut<Scoreboard>,
+    mut lives: ResMut<Lives>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut power_ups: ResMut<ActivePowerUps>,
+    mut ball_speed: ResMut<BallSpeed>,
+    mut rng: ResMut<GameRng>,
+    mut paddle_query: Query<(&mut Transform, &mut Velocity), With<Paddle>>,
+    ball_query: Query<Entity, With<Ball>>,
+    brick_query: Query<Entity, With<Brick>>,
+) {
+    saved_run.continuing = false;
+    // take() leaves None behind, the run is only continued once
+    let Some(game) = saved_run.game.take() else {
+        return;
+    };
+    if let Some(path) = data_file_path(SAVE_FILE_NAME) {
+        if let Err(err) = std::fs::remove_file(path) {
+            warn!("could not remove the saved run: {err}");
+        }
+    }
+
+    current_level.index = game.level_index;
+    current_level.starting_score = game.level_starting_score;
+    score.score = game.score;
+    lives.remaining = game.lives;
+    *ball_speed = game.ball_speed.into();
+    power_ups.clear();
+    for (kind, seconds) in game.power_ups {
+        power_ups.set_remaining(kind, seconds);
+    }
+
+    for (mut transform, mut velocity) in &mut paddle_query {
+        transform.translation = Vec3::from_array(game.paddle_position);
+        velocity.0 = Vec2::from_array(game.paddle_velocity);
+    }
+
+    for entity in ball_query.iter().chain(brick_query.iter()) {
+        commands.entity(entity).despawn();
+    }
+    for saved_ball in game.balls {
+        let ball = spawn_ball(
+            &mut commands,
+            &asset_server,
+            &config,
+            &mut *rng,
+            Vec3::from_array(saved_ball.position),
+            Vec2::from_array(saved_ball.velocity),
+        );
+        commands.entity(ball).insert(Spin {
+            rate: saved_ball.spin,
+        });
+        if let Some(stuck) = saved_ball.stuck {
+            commands.entity(ball).insert(if stuck.serve {
+                StuckToPaddle::serve()
+            } else {
+                StuckToPaddle::caught(stuck.offset)
+            });
+        }
+    }
+    for saved_brick in game.bricks {
+        let [r, g, b, a] = saved_brick.full_health_color;
+        let full_health = Color::rgba(r, g, b, a);
+        let [r, g, b, a] = saved_brick.last_hit_color;
+        let last_hit = Color::rgba(r, g, b, a);
+        let brick = spawn_brick(
+            &mut commands,
+            &config,
+            Vec2::from_array(saved_brick.position),
+            Brick {
+                health: saved_brick.health,
+                max_health: saved_brick.max_health,
+                hit_score: saved_brick.hit_score,
+                destroy_score: saved_brick.destroy_score,
+                kind: saved_brick.kind,
+            },
+            BrickColors {
+                full_health,
+                last_hit,
+            },
+        );
+        // a broken regenerating brick is hidden and can't be hit until it comes back, like in damage_bricks
+        if let Some(seconds) = saved_brick.regenerating {
+            commands.entity(brick).remove::<Collider>().insert((
+                Visibility::Hidden,
+                RegenerationTimer(Timer::from_seconds(seconds, TimerMode::Once)),
+            ));
+        }
+    }
+    for (position, kind) in game.capsules {
+        spawn_capsule(&mut commands, Vec2::from_array(position), kind);
+    }
+    for position in game.laser_bolts {
+        spawn_laser_bolt(&mut commands, Vec2::from_array(position));
+    }
+    // last, because spawn_ball above takes random numbers for the ball colors
+    *rng = game.rng;
+}
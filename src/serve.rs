@@ -0,0 +1,137 @@
+use bevy::prelude::*;
+
+use crate::{
+    ball_speed::BallSpeed,
+    config::GameConfig,
+    controls::InputActions,
+    paddle_bounce_velocity,
+    powerup::{ActivePowerUps, PowerUpKind},
+    tilted_up, Ball, Collider, Paddle, Velocity,
+};
+
+// ** Serving **
+// At the start of a round and after every lost life, the ball rests on the middle of the paddle and moves with it.
+// The launch action serves it: the further the paddle is from the middle of the arena, the more the ball leans to that side.
+// A ball that isn't served within SERVE_AUTO_LAUNCH_TIME seconds is launched by itself.
+// Balls caught by the StickyPaddle power-up are held the same way, and are launched like a paddle bounce.
+
+const SERVE_AUTO_LAUNCH_TIME: f32 = 3.0;
+
+// a ball held on top of the paddle, `offset` away from its middle
+#[derive(Component)]
+pub struct StuckToPaddle {
+    offset: f32,
+    // true for a ball waiting to be served, false for one caught by the sticky paddle
+    serve: bool,
+    // launches the ball once it finishes
+    auto_launch: Timer,
+}
+
+// a query filter for the balls that move on their own, the ones not held by the paddle
+pub type FreeBall = (With<Ball>, Without<StuckToPaddle>);
+
+impl StuckToPaddle {
+    // a ball waiting to be served from the middle of the paddle
+    pub fn serve() -> Self {
+        StuckToPaddle {
+            offset: 0.0,
+            serve: true,
+            auto_launch: Timer::from_seconds(SERVE_AUTO_LAUNCH_TIME, TimerMode::Once),
+        }
+    }
+
+    // a ball caught by the sticky paddle where it landed
+    pub fn caught(offset: f32) -> Self {
+        StuckToPaddle {
+            offset,
+            serve: false,
+            auto_launch: Timer::from_seconds(SERVE_AUTO_LAUNCH_TIME, TimerMode::Once),
+        }
+    }
+
+    pub fn offset(&self) -> f32 {
+        self.offset
+    }
+
+    pub fn is_serve(&self) -> bool {
+        self.serve
+    }
+}
+
+// keeps the stuck balls sitting on top of the paddle as it moves
+pub fn hold_stuck_balls(
+    paddle_query: Query<(&Transform, &Collider), With<Paddle>>,
+    mut ball_query: Query<(&mut Transform, &Ball, &StuckToPaddle), Without<Paddle>>,
+) {
+    let Ok((paddle_transform, paddle_collider)) = paddle_query.get_single() else {
+        return;
+    };
+    for (mut ball_transform, ball, stuck) in &mut ball_query {
+        ball_transform.translation.x = paddle_transform.translation.x + stuck.offset;
+        ball_transform.translation.y =
+            paddle_transform.translation.y + paddle_collider.size.y * 0.5 + ball.radius;
+    }
+}
+
+// the launch action (space by default) launches the stuck balls, and so does their auto launch timer.
+// Caught balls are also let go when StickyPaddle ends.
+// This runs in Update and not FixedUpdate: launch_pressed is only true for one frame, which a FixedUpdate tick could miss
+#[allow(clippy::too_many_arguments)]
+pub fn launch_stuck_balls(
+    mut commands: Commands,
+    config: Res<GameConfig>,
+    actions: Res<InputActions>,
+    time: Res<Time>,
+    power_ups: Res<ActivePowerUps>,
+    ball_speed: Res<BallSpeed>,
+    paddle_query: Query<(&Transform, &Collider), With<Paddle>>,
+    mut ball_query: Query<(Entity, &Transform, &mut Velocity, &mut StuckToPaddle)>,
+) {
+    let Ok((paddle_transform, paddle_collider)) = paddle_query.get_single() else {
+        return;
+    };
+    let launch_pressed = actions.launch_pressed;
+    let sticky = power_ups.is_active(PowerUpKind::StickyPaddle);
+
+    for (ball_entity, ball_transform, mut ball_velocity, mut stuck) in &mut ball_query {
+        stuck.auto_launch.tick(time.delta());
+        let released = !stuck.serve && !sticky;
+        if !launch_pressed && !stuck.auto_launch.finished() && !released {
+            continue;
+        }
+
+        ball_velocity.0 = if stuck.serve {
+            ball_speed.current
+                * serve_direction(
+                    &config,
+                    paddle_transform.translation.x,
+                    paddle_collider.size.x,
+                )
+        } else {
+            paddle_bounce_velocity(
+                &config,
+                ball_speed.current,
+                ball_transform.translation.x,
+                paddle_transform.translation.x,
+                paddle_collider.size.x,
+            )
+        };
+        commands.entity(ball_entity).remove::<StuckToPaddle>();
+    }
+}
+
+// in the middle of the arena the serve goes up at ball_launch_min_angle (to the right), and the closer the paddle is
+// to a side wall, the more it leans towards that wall, up to ball_launch_max_angle
+fn serve_direction(config: &GameConfig, paddle_x: f32, paddle_width: f32) -> Vec2 {
+    let arena_middle = (config.left_wall + config.right_wall) * 0.5;
+    // how far the middle of the paddle can go from the middle of the arena before it touches a wall
+    let paddle_reach =
+        (config.right_wall - config.left_wall - config.wall_thickness - paddle_width) * 0.5;
+    // -1.0 against the left wall, 0.0 in the middle, 1.0 against the right wall
+    let aim = ((paddle_x - arena_middle) / paddle_reach).clamp(-1.0, 1.0);
+
+    let side = if aim < 0.0 { -1.0 } else { 1.0 };
+    let angle = config.ball_launch_min_angle
+        + aim.abs() * (config.ball_launch_max_angle - config.ball_launch_min_angle);
+    tilted_up(side * angle)
+}